@@ -0,0 +1,43 @@
+//! A sync node for trying the sync protocol with two processes on one machine:
+//!
+//! cargo run --example sync_node -- serve node_a my_space 127.0.0.1:7878
+//! cargo run --example sync_node -- sync node_b my_space 127.0.0.1:7878 [--dry-run]
+//!
+//! Both store their spaces under their own root (node_a, node_b) instead of infospace.
+
+use std::env;
+use std::net::TcpListener;
+use std::process;
+
+use ig_tr::tr_service_fs::TrServiceFS;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.len() < 4 {
+        eprintln!("usage: sync_node serve|sync <root> <space id> <address> [--dry-run]");
+        process::exit(2);
+    }
+    let mut service = TrServiceFS::new_in(&args[1], args[2].clone());
+
+    match args[0].as_str() {
+        "serve" => {
+            let listener = TcpListener::bind(&args[3]).expect("something went wrong binding the address");
+            println!("serving {} on {}", args[2], listener.local_addr().unwrap());
+            service.serve_sync(&listener, None).expect("something went wrong serving");
+        },
+        "sync" => {
+            let dry_run = args.iter().any(|x| x == "--dry-run");
+            let report = service.sync_with_peer(&args[3], dry_run).expect("something went wrong syncing");
+            for (table, sync) in &report.tables {
+                println!("{}: {} added here, {} added there", table, sync.added_here, sync.added_there);
+            }
+            if report.dry_run {
+                println!("dry run, nothing was written");
+            }
+        },
+        _ => {
+            eprintln!("unknown command {}", args[0]);
+            process::exit(2);
+        },
+    }
+}
@@ -0,0 +1,141 @@
+use crate::async_triple_store::AsyncTripleStore;
+use crate::{InfoTriple, InfoTable};
+use crate::async_tr_director::AsyncTrDirector;
+use crate::async_triple_facade::AsyncTripleFacade;
+use crate::async_triple_store_fs::AsyncTripleStoreFS;
+
+///
+/// The async version of TrServiceFS.
+///
+#[derive(Clone)]
+pub struct AsyncTrServiceFS {
+    pub director: AsyncTrDirector<AsyncTripleStoreFS>,
+}
+
+impl AsyncTrServiceFS {
+
+    pub fn new(space_id: String) -> Self {
+        AsyncTrServiceFS {
+            director: AsyncTrDirector::new(AsyncTripleFacade{ storage: AsyncTripleStoreFS::new(space_id)}),
+        }
+    }
+
+    pub async fn get_all_info_triples_from_info_table(&mut self, infotable_name: String) -> Vec<InfoTriple> {
+        self.director.get_all_info_triples_from_info_table(infotable_name).await
+    }
+
+    pub async fn get_n_flatten_all_from_table_list(&mut self, table_list: Vec<String>) -> InfoTable {
+        self.director.get_n_flatten_all_from_table_list(table_list).await
+    }
+
+    pub async fn create_infotriple(&mut self, id1: String, id2: String) -> InfoTriple {
+        self.director.create_triple(id1, id2).await
+    }
+
+    pub async fn create_infotriples(&mut self, pairs: Vec<(String, String)>) -> Vec<InfoTriple> {
+        self.director.create_triples(pairs).await
+    }
+
+    pub async fn create_symmetric_infotriple(&mut self, id1: String, id2: String) -> InfoTriple {
+        self.director.create_symmetric_triple(id1, id2).await
+    }
+
+    pub async fn find_triple(&mut self, id1: String, id2: String) -> Option<InfoTriple> {
+        self.director.find_triple(id1, id2).await
+    }
+
+    pub async fn find_triple_in_table_list(&mut self, id1: String, id2: String, table_list: Vec<String>) -> Option<InfoTriple> {
+        self.director.find_triple_in_table_list(id1, id2, table_list).await
+    }
+
+    pub async fn get_triple(&mut self, infotable_name: String, triple_id: String) -> Option<InfoTriple> {
+        self.director.get_triple(infotable_name, triple_id).await
+    }
+
+    pub async fn contains_triple(&mut self, infotable_name: String, triple_id: String) -> bool {
+        self.director.contains_triple(infotable_name, triple_id).await
+    }
+
+    pub async fn clear_infotable(&mut self, infotable_name: String) {
+        self.director.clear_infotable(infotable_name).await;
+    }
+
+
+    //guest functions
+    pub async fn get_all_info_triples_from_info_table_guest_space(&mut self, infotable_name: String, space_id: String) -> Vec<InfoTriple> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.get_all_info_triples_from_info_table(infotable_name).await;
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub async fn get_n_flatten_all_from_table_list_guest_space(&mut self, table_list: Vec<String>, space_id: String) -> InfoTable {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.get_n_flatten_all_from_table_list(table_list).await;
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub async fn create_infotriple_guest_space(&mut self, id1: String, id2: String, space_id: String) -> InfoTriple {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.create_infotriple(id1, id2).await;
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub async fn create_infotriples_guest_space(&mut self, pairs: Vec<(String, String)>, space_id: String) -> Vec<InfoTriple> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.create_infotriples(pairs).await;
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub async fn create_symmetric_infotriple_guest_space(&mut self, id1: String, id2: String, space_id: String) -> InfoTriple {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.create_symmetric_infotriple(id1, id2).await;
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub async fn find_triple_guest_space(&mut self, id1: String, id2: String, space_id: String) -> Option<InfoTriple> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.find_triple(id1, id2).await;
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub async fn get_triple_guest_space(&mut self, infotable_name: String, triple_id: String, space_id: String) -> Option<InfoTriple> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.get_triple(infotable_name, triple_id).await;
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub async fn contains_triple_guest_space(&mut self, infotable_name: String, triple_id: String, space_id: String) -> bool {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.contains_triple(infotable_name, triple_id).await;
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub async fn clear_infotable_guest_space(&mut self, infotable_name: String, space_id: String) {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        self.clear_infotable(infotable_name).await;
+        self.director.triples.storage.revert_space_id();
+    }
+}
+
+
+#[tokio::test]
+async fn async_service_test() {
+    let mut service = AsyncTrServiceFS::new("async_service_test_space".to_string());
+    service.clear_infotable("main_table".to_string()).await;
+
+    let it = service.create_infotriple("id1-a".to_string(), "id2-a".to_string()).await;
+    let its = service.create_infotriples(vec![("id1-b".to_string(), "id2-b".to_string())]).await;
+
+    assert_eq!(service.find_triple("id1-a".to_string(), "id2-a".to_string()).await, Some(it));
+    assert_eq!(service.get_triple("main_table".to_string(), its[0].id.clone()).await, Some(its[0].clone()));
+    assert_eq!(service.get_all_info_triples_from_info_table("main_table".to_string()).await.len(), 2);
+    assert!(service.find_triple_guest_space("id1-a".to_string(), "id2-a".to_string(), "other_test_space".to_string()).await.is_none());
+}
@@ -1,11 +1,23 @@
 pub mod logic;
 pub use logic::tr_director;
+#[cfg(feature = "async")]
+pub use logic::async_tr_director;
 
 pub mod model;
-pub use model::{info_table::InfoTable, info_triple::InfoTriple};
+pub use model::{info_table::InfoTable, info_triple::{InfoTriple, TriplePosition}, construction_tree::{ConstructionTree, ConstructionNode}, dag_report::DagReport, sync_report::{SyncReport, TableSync}, merkle_tree::{MerkleTree, MerkleProof, ProofStep, verify_inclusion}, space_patch::{SpacePatch, TablePatch, PatchConflict}, table_version::TableVersion};
 
 pub mod store;
-pub use store::{triple_facade, triple_store, triple_store_fs};
+pub use store::{triple_facade, triple_store, triple_store_fs, table_file, table_format, table_compression, table_encryption, store_error, cached_store, overlay_store};
+#[cfg(feature = "sqlite")]
+pub use store::triple_store_sqlite;
+#[cfg(feature = "kv")]
+pub use store::triple_store_kv;
+#[cfg(feature = "async")]
+pub use store::{async_triple_facade, async_triple_store, async_triple_store_fs};
 
 pub mod tr_service_fs;
 pub mod tr_single_service_fs;
+pub mod tr_shared_service_fs;
+pub mod tr_sync_fs;
+#[cfg(feature = "async")]
+pub mod async_tr_service_fs;
@@ -1 +1,3 @@
 pub mod tr_director;
+#[cfg(feature = "async")]
+pub mod async_tr_director;
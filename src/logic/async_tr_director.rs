@@ -0,0 +1,75 @@
+use ig_tools::hashing_tools;
+use crate::{async_triple_facade::AsyncTripleFacade, async_triple_store::AsyncTripleStore, InfoTriple, InfoTable};
+
+///
+/// The async version of TrDirector.
+///
+#[derive(Clone)]
+pub struct AsyncTrDirector<S:AsyncTripleStore> {
+   pub triples: AsyncTripleFacade<S>,
+}
+
+impl <S:AsyncTripleStore> AsyncTrDirector<S>{
+
+    pub fn new(triples: AsyncTripleFacade<S>) -> AsyncTrDirector<S> {
+        AsyncTrDirector{triples}
+    }
+
+    pub async fn get_all_info_triples_from_info_table(&mut self, infotable_name: String) -> Vec<InfoTriple>{
+        self.triples.get_all_info_triples_from_info_table(infotable_name.as_str()).await
+    }
+
+    pub async fn get_n_flatten_all_from_table_list(&mut self, table_list: Vec<String>) -> InfoTable {
+        let mut triples: Vec<InfoTriple> = Vec::new();
+        for t in table_list {
+            triples.extend(self.get_all_info_triples_from_info_table(t).await);
+        }
+        triples.into_iter().collect()
+    }
+
+    pub async fn create_triple(&mut self, id1: String, id2: String) -> InfoTriple {
+        let it = InfoTriple::from(hashing_tools::concat_n_hash(id1.as_str(), id2.as_str()));
+        self.triples.add_to_infotable("main_table".to_string(),it.clone()).await;
+        it
+    }
+
+    pub async fn create_triples(&mut self, pairs: Vec<(String, String)>) -> Vec<InfoTriple> {
+        let its: Vec<InfoTriple> = pairs
+            .iter()
+            .map(|(id1, id2)| InfoTriple::from(hashing_tools::concat_n_hash(id1.as_str(), id2.as_str())))
+            .collect();
+        self.triples.add_many_to_infotable("main_table".to_string(), its.clone()).await;
+        its
+    }
+
+    pub async fn create_symmetric_triple(&mut self, id1: String, id2: String) -> InfoTriple {
+        let (id1, id2) = InfoTriple::canonical_pair(id1, id2);
+        self.create_triple(id1, id2).await
+    }
+
+    pub async fn find_triple(&mut self, id1: String, id2: String) -> Option<InfoTriple> {
+        self.find_triple_in_table_list(id1, id2, vec!["main_table".to_string()]).await
+    }
+
+    pub async fn find_triple_in_table_list(&mut self, id1: String, id2: String, table_list: Vec<String>) -> Option<InfoTriple> {
+        let it = InfoTriple::from(hashing_tools::concat_n_hash(id1.as_str(), id2.as_str()));
+        for t in table_list {
+            if let Some(found) = self.triples.get_triple(&t, &it.id).await {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    pub async fn get_triple(&mut self, infotable_name: String, triple_id: String) -> Option<InfoTriple> {
+        self.triples.get_triple(&infotable_name, &triple_id).await
+    }
+
+    pub async fn contains_triple(&mut self, infotable_name: String, triple_id: String) -> bool {
+        self.triples.contains_triple(&infotable_name, &triple_id).await
+    }
+
+    pub async fn clear_infotable(&mut self, infotable_name: String) {
+        self.triples.clear_infotable(infotable_name).await;
+    }
+}
@@ -1,6 +1,6 @@
 
 use ig_tools::hashing_tools;
-use crate::{triple_facade::TripleFacade, triple_store::TripleStore, InfoTriple, InfoTable};
+use crate::{triple_facade::TripleFacade, triple_store::TripleStore, InfoTriple, InfoTable, SyncReport, MerkleProof, TableVersion};
 
 #[derive(Clone)]
 pub struct TrDirector<S:TripleStore> {
@@ -35,7 +35,80 @@ impl <S:TripleStore> TrDirector<S>{
         it
     }
 
+    ///
+    /// Like create_triple() but for many pairs at once, which are stored with a single call to
+    /// the storage.
+    ///
+    pub fn create_triples(&mut self, pairs: Vec<(String, String)>) -> Vec<InfoTriple> {
+        let its: Vec<InfoTriple> = pairs
+            .iter()
+            .map(|(id1, id2)| InfoTriple::from(hashing_tools::concat_n_hash(id1.as_str(), id2.as_str())))
+            .collect();
+        self.triples.add_many_to_infotable("main_table".to_string(), its.clone());
+        its
+    }
+
+    ///
+    /// Like create_triple() but for symmetric relations, so the pair is put in canonical order
+    /// before hashing and (a, b) gives the same triple as (b, a).
+    ///
+    pub fn create_symmetric_triple(&mut self, id1: String, id2: String) -> InfoTriple {
+        let (id1, id2) = InfoTriple::canonical_pair(id1, id2);
+        self.create_triple(id1, id2)
+    }
+
+    ///
+    /// Returns the triple pairing id1 with id2 if it has been created. Since the triple id is
+    /// deterministic we only have to compute it and look it up.
+    ///
+    pub fn find_triple(&mut self, id1: String, id2: String) -> Option<InfoTriple> {
+        self.find_triple_in_table_list(id1, id2, vec!["main_table".to_string()])
+    }
+
+    ///
+    /// Like find_triple() but looks in each of the tables in table_list, returning the first
+    /// match.
+    ///
+    pub fn find_triple_in_table_list(&mut self, id1: String, id2: String, table_list: Vec<String>) -> Option<InfoTriple> {
+        let it = InfoTriple::from(hashing_tools::concat_n_hash(id1.as_str(), id2.as_str()));
+        table_list
+            .iter()
+            .find_map(|t| self.triples.get_triple(t, &it.id))
+    }
+
+    pub fn get_triple(&mut self, infotable_name: String, triple_id: String) -> Option<InfoTriple> {
+        self.triples.get_triple(&infotable_name, &triple_id)
+    }
+
+    pub fn contains_triple(&mut self, infotable_name: String, triple_id: String) -> bool {
+        self.triples.contains_triple(&infotable_name, &triple_id)
+    }
+
     pub fn clear_infotable(&mut self, infotable_name: String) {
         self.triples.clear_infotable(infotable_name);
     }
+
+    pub fn sync_with<U: TripleStore>(&mut self, other: &mut TrDirector<U>, dry_run: bool) -> SyncReport {
+        self.triples.sync_with(&mut other.triples, dry_run)
+    }
+
+    pub fn get_info_table_version(&mut self, infotable_name: String) -> TableVersion {
+        self.triples.get_info_table_version(&infotable_name)
+    }
+
+    pub fn get_info_table_as_of(&mut self, infotable_name: String, version: TableVersion) -> Option<Vec<InfoTriple>> {
+        self.triples.get_info_table_as_of(&infotable_name, version)
+    }
+
+    pub fn get_table_root(&mut self, infotable_name: String) -> String {
+        self.triples.get_table_root(&infotable_name)
+    }
+
+    pub fn get_space_root(&mut self) -> String {
+        self.triples.get_space_root()
+    }
+
+    pub fn get_inclusion_proof(&mut self, infotable_name: String, triple_id: String) -> Option<MerkleProof> {
+        self.triples.get_inclusion_proof(&infotable_name, &triple_id)
+    }
 }
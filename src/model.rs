@@ -1,2 +1,8 @@
 pub mod info_triple;
 pub mod info_table;
+pub mod construction_tree;
+pub mod dag_report;
+pub mod sync_report;
+pub mod merkle_tree;
+pub mod space_patch;
+pub mod table_version;
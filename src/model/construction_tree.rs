@@ -0,0 +1,131 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+///
+/// A node in a ConstructionTree, telling what an id turned out to be when it was expanded.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstructionNode {
+    /// The id is not a triple id in the table (or it is an add_node dummy triple), so it cannot
+    /// be decomposed any further.
+    Leaf,
+    /// The id is a triple id constructed from id1 and id2.
+    Triple { id1: String, id2: String },
+    /// The id is a triple id, but it was not expanded because the depth limit was reached.
+    Truncated,
+}
+
+///
+/// The result of recursively decomposing a triple id into the ids it was constructed from.
+/// Since the same id can be used by several triples the structure is really a DAG, so every id
+/// is expanded only once and stored in nodes, while the tree shape is given by following id1
+/// and id2 from the root.
+///
+#[derive(Debug, Clone)]
+pub struct ConstructionTree {
+    pub root: String,
+    pub nodes: HashMap<String, ConstructionNode>,
+}
+
+impl ConstructionTree {
+
+    ///
+    /// Creates a new tree with only the root id and no expanded nodes.
+    ///
+    pub fn new(root: String) -> Self {
+        ConstructionTree {
+            root,
+            nodes: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Returns the node for an id if the id is part of the tree.
+    ///
+    pub fn get_node(&self, id: &str) -> Option<&ConstructionNode> {
+        self.nodes.get(id)
+    }
+
+    ///
+    /// Returns the ids of the (sorted) leaves of the tree, that is, the ids the root is
+    /// ultimately made of.
+    ///
+    pub fn leaves(&self) -> Vec<String> {
+        let mut leaves: Vec<String> = self.nodes
+            .iter()
+            .filter(|(_, node)| **node == ConstructionNode::Leaf)
+            .map(|(id, _)| id.to_string())
+            .collect();
+        leaves.sort();
+        leaves
+    }
+
+    ///
+    /// Returns the ids of the (sorted) triples inside the tree, including the root if it is a
+    /// triple.
+    ///
+    pub fn triple_ids(&self) -> Vec<String> {
+        let mut ids: Vec<String> = self.nodes
+            .iter()
+            .filter(|(_, node)| matches!(node, ConstructionNode::Triple{..}))
+            .map(|(id, _)| id.to_string())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    ///
+    /// Returns true if no node was left unexpanded because of the depth limit.
+    ///
+    pub fn is_complete(&self) -> bool {
+        !self.nodes.values().any(|node| *node == ConstructionNode::Truncated)
+    }
+
+    fn fmt_node(&self, f: &mut fmt::Formatter<'_>, id: &str, indent: usize, printed: &mut HashSet<String>) -> fmt::Result {
+        let pad = "  ".repeat(indent);
+        match self.nodes.get(id) {
+            Some(ConstructionNode::Triple { id1, id2 }) => {
+                if !printed.insert(id.to_string()) {
+                    return writeln!(f, "{}{} (see above)", pad, id);
+                }
+                writeln!(f, "{}{}", pad, id)?;
+                self.fmt_node(f, id1, indent + 1, printed)?;
+                self.fmt_node(f, id2, indent + 1, printed)
+            },
+            Some(ConstructionNode::Truncated) => writeln!(f, "{}{} ...", pad, id),
+            _ => writeln!(f, "{}{}", pad, id),
+        }
+    }
+}
+
+///
+/// Pretty prints the tree with one id per line, indented by depth. A triple that has already
+/// been printed is only referred to the second time so shared subtrees are shown once, and
+/// triples left out by the depth limit are marked with "...".
+///
+impl fmt::Display for ConstructionTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_node(f, &self.root, 0, &mut HashSet::new())
+    }
+}
+
+
+#[test]
+fn construction_tree_display_test() {
+    let mut tree = ConstructionTree::new("id-c".to_string());
+    tree.nodes.insert("id-c".to_string(), ConstructionNode::Triple{ id1: "id-b".to_string(), id2: "id-b".to_string() });
+    tree.nodes.insert("id-b".to_string(), ConstructionNode::Triple{ id1: "leaf-1".to_string(), id2: "id-a".to_string() });
+    tree.nodes.insert("leaf-1".to_string(), ConstructionNode::Leaf);
+    tree.nodes.insert("id-a".to_string(), ConstructionNode::Truncated);
+
+    let ideal: String = String::from(
+"id-c
+  id-b
+    leaf-1
+    id-a ...
+  id-b (see above)
+");
+    assert_eq!(tree.to_string(), ideal);
+    assert!(!tree.is_complete());
+    assert_eq!(tree.leaves(), vec!["leaf-1".to_string()]);
+}
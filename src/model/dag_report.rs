@@ -0,0 +1,29 @@
+
+///
+/// The result of validating that an InfoTable is a DAG through the id -> id1/id2 relation, which
+/// every table built through TrDirector::create_triple() is, but imported or hand appended data
+/// might not be.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DagReport {
+    /// Groups of triple ids (sorted) that reach each other through id1/id2, i.e. each group
+    /// contains at least one cycle.
+    pub cycles: Vec<Vec<String>>,
+    /// Triple ids (sorted) that use themselves as id1 or id2 without being an add_node dummy
+    /// triple (id, id, id).
+    pub self_references: Vec<String>,
+    /// Triple ids in construction order, so every triple comes after the triples it was
+    /// constructed from. Triples in or depending on a cycle or a self reference are left out.
+    pub topological_order: Vec<String>,
+}
+
+impl DagReport {
+
+    ///
+    /// Returns true if neither cycles nor self references were found, which also means that
+    /// topological_order contains all triples of the table.
+    ///
+    pub fn is_dag(&self) -> bool {
+        self.cycles.is_empty() && self.self_references.is_empty()
+    }
+}
@@ -1,8 +1,8 @@
 use std::error::Error;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::vec::IntoIter;
 
-use crate::InfoTriple;
+use crate::{InfoTriple, TriplePosition, ConstructionTree, ConstructionNode, DagReport};
     
 #[allow(dead_code)]
 #[derive(Debug,Clone)]
@@ -305,6 +305,304 @@ impl InfoTable {
     // ----------- Query VECTORS part end --------------------
 
 
+    // ----------- Directional part begin --------------------
+
+    ///
+    /// Like get_neighbors_as_triples() but only the triples where the id is ID1, so the
+    /// pairings going out from the id.
+    ///
+    pub fn get_outgoing_as_triples(&self, id: String) -> InfoTable {
+        let mut refs = InfoTable::new();
+        self
+            .rows
+            .iter()
+            .filter(|(_x,(y,_z))|{ *y == id })
+            .for_each(|(x,(y,z))|{
+                let _ = refs.add(x,y,z);
+            });
+        refs
+    }
+
+    ///
+    /// Like get_neighbors_as_triples() but only the triples where the id is ID2, so the
+    /// pairings coming in to the id.
+    ///
+    pub fn get_incoming_as_triples(&self, id: String) -> InfoTable {
+        let mut refs = InfoTable::new();
+        self
+            .rows
+            .iter()
+            .filter(|(_x,(_y,z))|{ *z == id })
+            .for_each(|(x,(y,z))|{
+                let _ = refs.add(x,y,z);
+            });
+        refs
+    }
+
+    ///
+    /// Returns the ID2s of the triples where the id is ID1.
+    ///
+    pub fn get_outgoing_ids(&self, id: String) -> Vec<String> {
+        self.get_outgoing_as_triples(id)
+            .rows
+            .into_values()
+            .map(|(_,z)|z)
+            .collect()
+    }
+
+    ///
+    /// Returns the ID1s of the triples where the id is ID2.
+    ///
+    pub fn get_incoming_ids(&self, id: String) -> Vec<String> {
+        self.get_incoming_as_triples(id)
+            .rows
+            .into_values()
+            .map(|(y,_)|y)
+            .collect()
+    }
+
+    ///
+    /// The position aware version of get_neighbor_ids_and_triple_ids(). The key is the triple_id
+    /// and the value is the position the id has in that triple together with the neighbor_id.
+    ///
+    pub fn get_neighbor_positions(&self, id: String) -> HashMap<String,(TriplePosition,String)> {
+        self.get_neighbors_as_triples(id.clone())
+            .rows
+            .into_iter()
+            .map(|(x,(y,z))|{
+                let triple = InfoTriple::from((x.clone(),y,z));
+                let position = triple.position_of(&id).unwrap();
+                let neighbor = if position == TriplePosition::Id2 { triple.id1 } else { triple.id2 };
+                (x, (position, neighbor))
+            })
+            .collect()
+    }
+
+    ///
+    /// Returns the triple pairing id1 and id2 in either order, as used for symmetric relations.
+    /// Should the table hold both orders, the one in canonical order (see
+    /// InfoTriple::canonical_pair()) is returned.
+    ///
+    pub fn find_unordered_pair(&self, id1: String, id2: String) -> Option<InfoTriple> {
+        let (first, second) = InfoTriple::canonical_pair(id1, id2);
+        self.find_pair(first.clone(), second.clone())
+            .or_else(|| self.find_pair(second, first))
+    }
+
+    ///
+    /// Returns the triple pairing id1 as ID1 with id2 as ID2, if the two are paired in this
+    /// InfoTable.
+    ///
+    pub fn find_pair(&self, id1: String, id2: String) -> Option<InfoTriple> {
+        self.rows
+            .iter()
+            .find(|(_x,(y,z))|{ *y == id1 && *z == id2 })
+            .map(|(x,(y,z))| InfoTriple::new(x, y, z))
+    }
+
+    // ----------- Directional part end --------------------
+
+
+    // ----------- Recursive part begin --------------------
+
+    ///
+    /// Expands an id recursively into the ids it was constructed from, that is, id1 and id2 of
+    /// the triple with the id as triple id, then id1 and id2 of those and so on until we reach ids
+    /// that are not triple ids in this InfoTable (the leaves).
+    /// max_depth limits how many levels below the root are expanded (None means no limit), and
+    /// every id is only expanded once, so shared subtrees are reused and a malformed table with
+    /// cycles cannot make us loop forever.
+    ///
+    pub fn decompose(&self, id: String, max_depth: Option<usize>) -> ConstructionTree {
+        let mut tree = ConstructionTree::new(id.clone());
+        let mut queue: VecDeque<(String, usize)> = VecDeque::new();
+        queue.push_back((id, 0));
+
+        //breadth first, so an id is always expanded at the lowest depth it occurs at
+        while let Some((id, depth)) = queue.pop_front() {
+            if tree.nodes.contains_key(&id) {
+                continue;
+            }
+            let node = match self.rows.get(&id) {
+                None => ConstructionNode::Leaf,
+                Some((id1, id2)) if *id1 == id && *id2 == id => ConstructionNode::Leaf,
+                Some(_) if max_depth.is_some_and(|max| depth >= max) => ConstructionNode::Truncated,
+                Some((id1, id2)) => {
+                    queue.push_back((id1.to_string(), depth + 1));
+                    queue.push_back((id2.to_string(), depth + 1));
+                    ConstructionNode::Triple { id1: id1.to_string(), id2: id2.to_string() }
+                },
+            };
+            tree.nodes.insert(id, node);
+        }
+        tree
+    }
+
+    ///
+    /// The inverse of decompose(). Returns all triples that use the id as ID1 or ID2, then all
+    /// triples using those triple ids and so on up to the triples nobody uses (the roots).
+    /// The key is the triple id of the ancestor and the value is its level, where 1 means that
+    /// the triple uses the id directly. An ancestor reachable in several ways gets its lowest
+    /// level.
+    ///
+    pub fn get_ancestors(&self, id: String) -> HashMap<String, usize> {
+        let mut users: HashMap<&str, Vec<&str>> = HashMap::new();
+        self.rows
+            .iter()
+            .for_each(|(x,(y,z))|{
+                users.entry(y.as_str()).or_default().push(x.as_str());
+                if y != z {
+                    users.entry(z.as_str()).or_default().push(x.as_str());
+                }
+            });
+
+        let mut ancestors: HashMap<String, usize> = HashMap::new();
+        let mut queue: VecDeque<(&str, usize)> = VecDeque::new();
+        queue.push_back((id.as_str(), 0));
+
+        while let Some((id_x, level)) = queue.pop_front() {
+            users.get(id_x)
+                .into_iter()
+                .flatten()
+                .for_each(|user|{
+                    if *user != id && !ancestors.contains_key(*user) {
+                        ancestors.insert(user.to_string(), level + 1);
+                        queue.push_back((user, level + 1));
+                    }
+                });
+        }
+        ancestors
+    }
+
+    ///
+    /// Validates that the InfoTable is a DAG through the id -> id1/id2 relation, so that the
+    /// recursive methods are meaningful on it. Reports cycles, self references other than the
+    /// add_node() dummy triples and gives the triples in construction order.
+    ///
+    pub fn validate_dag(&self) -> DagReport {
+        let mut report = DagReport::default();
+
+        //edges from a triple id to the triple ids it was constructed from
+        let mut edges: HashMap<&str, Vec<&str>> = HashMap::new();
+        self.rows
+            .iter()
+            .for_each(|(x,(y,z))|{
+                let is_dummy = x == y && x == z;
+                if !is_dummy && (x == y || x == z) {
+                    report.self_references.push(x.to_string());
+                }
+                let mut components: Vec<&str> = [y.as_str(), z.as_str()]
+                    .into_iter()
+                    .filter(|c| *c != x && self.rows.contains_key(*c))
+                    .collect();
+                components.dedup();
+                edges.insert(x.as_str(), components);
+            });
+        report.self_references.sort();
+
+        report.cycles = Self::find_cycles(&edges);
+
+        //Kahn's algorithm, where a self reference counts as a component that is never ready
+        let mut pending: HashMap<&str, usize> = HashMap::new();
+        let mut users: HashMap<&str, Vec<&str>> = HashMap::new();
+        edges
+            .iter()
+            .for_each(|(x, components)|{
+                let self_ref = report.self_references.binary_search(&x.to_string()).is_ok();
+                pending.insert(x, components.len() + usize::from(self_ref));
+                components.iter().for_each(|c| users.entry(c).or_default().push(x));
+            });
+        let mut ready: BTreeSet<&str> = pending
+            .iter()
+            .filter(|(_, n)| **n == 0)
+            .map(|(x, _)| *x)
+            .collect();
+        while let Some(x) = ready.pop_first() {
+            report.topological_order.push(x.to_string());
+            users.get(x)
+                .into_iter()
+                .flatten()
+                .for_each(|user|{
+                    let n = pending.get_mut(user).unwrap();
+                    *n -= 1;
+                    if *n == 0 {
+                        ready.insert(user);
+                    }
+                });
+        }
+        report
+    }
+
+    ///
+    /// Tarjan's strongly connected components algorithm (iterative, so deep tables cannot
+    /// overflow the stack) returning the components with more than one triple id.
+    ///
+    fn find_cycles(edges: &HashMap<&str, Vec<&str>>) -> Vec<Vec<String>> {
+        let mut next_index: usize = 0;
+        let mut index: HashMap<&str, usize> = HashMap::new();
+        let mut low: HashMap<&str, usize> = HashMap::new();
+        let mut stack: Vec<&str> = Vec::new();
+        let mut on_stack: HashSet<&str> = HashSet::new();
+        let mut cycles: Vec<Vec<String>> = Vec::new();
+
+        let mut nodes: Vec<&str> = edges.keys().copied().collect();
+        nodes.sort();
+
+        for start in nodes {
+            if index.contains_key(start) {
+                continue;
+            }
+            index.insert(start, next_index);
+            low.insert(start, next_index);
+            next_index += 1;
+            stack.push(start);
+            on_stack.insert(start);
+            let mut call_stack: Vec<(&str, usize)> = vec![(start, 0)];
+
+            while let Some((v, i)) = call_stack.last().copied() {
+                if let Some(w) = edges[v].get(i).copied() {
+                    call_stack.last_mut().unwrap().1 += 1;
+                    if !index.contains_key(w) {
+                        index.insert(w, next_index);
+                        low.insert(w, next_index);
+                        next_index += 1;
+                        stack.push(w);
+                        on_stack.insert(w);
+                        call_stack.push((w, 0));
+                    } else if on_stack.contains(w) {
+                        let low_v = low[v].min(index[w]);
+                        low.insert(v, low_v);
+                    }
+                    continue;
+                }
+                call_stack.pop();
+                if let Some((u, _)) = call_stack.last() {
+                    let low_u = low[u].min(low[v]);
+                    low.insert(u, low_u);
+                }
+                if low[v] == index[v] {
+                    let mut component: Vec<String> = Vec::new();
+                    while let Some(w) = stack.pop() {
+                        on_stack.remove(w);
+                        component.push(w.to_string());
+                        if w == v {
+                            break;
+                        }
+                    }
+                    if component.len() > 1 {
+                        component.sort();
+                        cycles.push(component);
+                    }
+                }
+            }
+        }
+        cycles.sort();
+        cycles
+    }
+
+    // ----------- Recursive part end --------------------
+
+
     //TODO: future method that can take into account that say canceled triples can also be canceled and
     // those can be canceled and so on, so we need to go recursive....
 }
@@ -881,3 +1179,187 @@ fn get_info_triples_test() {
     assert!(iter.next().is_none());
 }
 
+
+    // ----------- Directional TEST part begin --------------------
+
+#[test]
+fn get_outgoing_and_incoming_test() {
+    let mut it = InfoTable::new();
+    let _ = it.add("id-a", "subject", "object-a");
+    let _ = it.add("id-b", "object-b", "subject");
+    let _ = it.add("id-c", "subject", "object-c");
+    let _ = it.add("id-d", "id1-d", "id2-d");
+
+    let mut outgoing = it.get_outgoing_as_triples(String::from("subject")).flatten_triples_ids_only();
+    outgoing.sort();
+    assert_eq!(outgoing, vec!["id-a", "id-c"]);
+
+    let mut outgoing_ids = it.get_outgoing_ids(String::from("subject"));
+    outgoing_ids.sort();
+    assert_eq!(outgoing_ids, vec!["object-a", "object-c"]);
+
+    let incoming = it.get_incoming_as_triples(String::from("subject")).flatten_triples_ids_only();
+    assert_eq!(incoming, vec!["id-b"]);
+    assert_eq!(it.get_incoming_ids(String::from("subject")), vec!["object-b"]);
+}
+
+#[test]
+fn get_neighbor_positions_test() {
+    let mut it = InfoTable::new();
+    let _ = it.add("id-a", "subject", "object-a");
+    let _ = it.add("id-b", "object-b", "subject");
+    let _ = it.add("id-d", "id1-d", "id2-d");
+    let _ = it.add_node("subject");
+
+    let positions = it.get_neighbor_positions(String::from("subject"));
+
+    assert_eq!(positions.len(), 3);
+    assert_eq!(positions.get("id-a"), Some(&(TriplePosition::Id1, String::from("object-a"))));
+    assert_eq!(positions.get("id-b"), Some(&(TriplePosition::Id2, String::from("object-b"))));
+    assert_eq!(positions.get("subject"), Some(&(TriplePosition::Both, String::from("subject"))));
+}
+
+#[test]
+fn find_unordered_pair_test() {
+    let mut it = InfoTable::new();
+    let _ = it.add("id-a", "id-y", "id-x");
+    let _ = it.add("id-b", "id-x", "id-z");
+
+    assert_eq!(it.find_unordered_pair(String::from("id-x"), String::from("id-y")).unwrap().id, "id-a");
+    assert_eq!(it.find_unordered_pair(String::from("id-y"), String::from("id-x")).unwrap().id, "id-a");
+    assert!(it.find_unordered_pair(String::from("id-y"), String::from("id-z")).is_none());
+
+    let _ = it.add("id-c", "id-x", "id-y");
+    assert_eq!(it.find_unordered_pair(String::from("id-y"), String::from("id-x")).unwrap().id, "id-c");
+}
+
+#[test]
+fn find_pair_test() {
+    let mut it = InfoTable::new();
+    let _ = it.add("id-a", "id-y", "id-x");
+    let _ = it.add("id-b", "id-x", "id-z");
+
+    assert_eq!(it.find_pair(String::from("id-y"), String::from("id-x")).unwrap().id, "id-a");
+    assert!(it.find_pair(String::from("id-x"), String::from("id-y")).is_none());
+}
+
+    // ----------- Directional TEST part end --------------------
+
+
+    // ----------- Recursive TEST part begin --------------------
+
+#[test]
+fn decompose_test() {
+    let mut it = InfoTable::new();
+    let _ = it.add("id-a", "leaf-1", "leaf-2");
+    let _ = it.add("id-b", "id-a", "leaf-3");
+    let _ = it.add("id-c", "id-b", "id-a");
+    let _ = it.add("id-x", "leaf-4", "leaf-5");
+    let _ = it.add_node("leaf-3");
+
+    let tree = it.decompose(String::from("id-c"), None);
+
+    assert!(tree.is_complete());
+    assert_eq!(tree.triple_ids(), vec!["id-a", "id-b", "id-c"]);
+    assert_eq!(tree.leaves(), vec!["leaf-1", "leaf-2", "leaf-3"]);
+    assert_eq!(
+        tree.get_node("id-b"),
+        Some(&ConstructionNode::Triple{ id1: String::from("id-a"), id2: String::from("leaf-3") })
+    );
+    assert_eq!(
+        tree.to_string(),
+"id-c
+  id-b
+    id-a
+      leaf-1
+      leaf-2
+    leaf-3
+  id-a (see above)
+"
+    );
+}
+
+#[test]
+fn decompose_max_depth_test() {
+    let mut it = InfoTable::new();
+    let _ = it.add("id-a", "leaf-1", "leaf-2");
+    let _ = it.add("id-b", "id-a", "leaf-3");
+    let _ = it.add("id-c", "id-b", "leaf-4");
+
+    let tree = it.decompose(String::from("id-c"), Some(1));
+
+    assert!(!tree.is_complete());
+    assert_eq!(tree.get_node("id-b"), Some(&ConstructionNode::Truncated));
+    assert_eq!(tree.get_node("id-a"), None);
+    assert_eq!(tree.leaves(), vec!["leaf-4"]);
+
+    let tree = it.decompose(String::from("leaf-1"), Some(0));
+    assert_eq!(tree.get_node("leaf-1"), Some(&ConstructionNode::Leaf));
+}
+
+#[test]
+fn decompose_cycle_test() {
+    let mut it = InfoTable::new();
+    let _ = it.add("id-a", "id-b", "leaf-1");
+    let _ = it.add("id-b", "id-a", "leaf-2");
+
+    let tree = it.decompose(String::from("id-a"), None);
+
+    assert_eq!(tree.triple_ids(), vec!["id-a", "id-b"]);
+    assert_eq!(tree.leaves(), vec!["leaf-1", "leaf-2"]);
+}
+
+#[test]
+fn get_ancestors_test() {
+    let mut it = InfoTable::new();
+    let _ = it.add("id-a", "leaf-1", "leaf-2");
+    let _ = it.add("id-b", "id-a", "leaf-3");
+    let _ = it.add("id-c", "id-b", "id-a");
+    let _ = it.add("id-d", "id-c", "leaf-4");
+    let _ = it.add("id-x", "leaf-4", "leaf-5");
+    let _ = it.add_node("leaf-1");
+
+    let ancestors = it.get_ancestors(String::from("leaf-1"));
+
+    assert_eq!(ancestors.len(), 4);
+    assert_eq!(ancestors.get("id-a"), Some(&1));
+    assert_eq!(ancestors.get("id-b"), Some(&2));
+    assert_eq!(ancestors.get("id-c"), Some(&2));
+    assert_eq!(ancestors.get("id-d"), Some(&3));
+
+    assert!(it.get_ancestors(String::from("id-d")).is_empty());
+}
+
+#[test]
+fn validate_dag_test() {
+    let mut it = InfoTable::new();
+    let _ = it.add("id-a", "leaf-1", "leaf-2");
+    let _ = it.add("id-b", "id-a", "leaf-3");
+    let _ = it.add("id-c", "id-b", "id-a");
+    let _ = it.add_node("leaf-1");
+
+    let report = it.validate_dag();
+
+    assert!(report.is_dag());
+    assert_eq!(report.topological_order, vec!["leaf-1", "id-a", "id-b", "id-c"]);
+}
+
+#[test]
+fn validate_dag_cycles_test() {
+    let mut it = InfoTable::new();
+    let _ = it.add("id-a", "id-b", "leaf-1");
+    let _ = it.add("id-b", "id-c", "leaf-2");
+    let _ = it.add("id-c", "id-a", "leaf-3");
+    let _ = it.add("id-d", "id-c", "leaf-4");
+    let _ = it.add("id-e", "id-e", "leaf-5");
+    let _ = it.add("id-f", "leaf-6", "leaf-7");
+
+    let report = it.validate_dag();
+
+    assert!(!report.is_dag());
+    assert_eq!(report.cycles, vec![vec!["id-a", "id-b", "id-c"]]);
+    assert_eq!(report.self_references, vec!["id-e"]);
+    assert_eq!(report.topological_order, vec!["id-f"]);
+}
+
+    // ----------- Recursive TEST part end --------------------
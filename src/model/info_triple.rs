@@ -48,6 +48,38 @@ impl InfoTriple {
             _ => false,
         } 
     }
+
+    ///
+    /// Returns the pair in canonical (sorted) order, so that a symmetric relation between two ids
+    /// always gets hashed into the same triple id no matter which order it was given in.
+    ///
+    pub fn canonical_pair(id1: String, id2: String) -> (String, String) {
+        if id1 <= id2 { (id1, id2) } else { (id2, id1) }
+    }
+
+    ///
+    /// Returns the position id_x has in the pairing, if any. Since concat_n_hash(id1, id2) is
+    /// order sensitive, this is what tells subject (ID1) from object (ID2).
+    ///
+    pub fn position_of(&self, id_x: &str) -> Option<TriplePosition> {
+        match (id_x == self.id1, id_x == self.id2) {
+            (true, true) => Some(TriplePosition::Both),
+            (true, false) => Some(TriplePosition::Id1),
+            (false, true) => Some(TriplePosition::Id2),
+            (false, false) => None,
+        }
+    }
+}
+
+///
+/// The position of an id in the pairing of an InfoTriple.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TriplePosition {
+    Id1,
+    Id2,
+    /// The id is both ID1 and ID2, e.g. in an add_node dummy triple.
+    Both,
 }
 
 impl From<String> for InfoTriple {
@@ -104,6 +136,21 @@ impl PartialEq for InfoTriple {
     }
 }
 
+#[test]
+fn position_of_test(){
+    let triple = InfoTriple::new("id", "id1", "id2");
+    assert_eq!(triple.position_of("id1"), Some(TriplePosition::Id1));
+    assert_eq!(triple.position_of("id2"), Some(TriplePosition::Id2));
+    assert_eq!(triple.position_of("id"), None);
+    assert_eq!(InfoTriple::new("id", "id", "id").position_of("id"), Some(TriplePosition::Both));
+}
+
+#[test]
+fn canonical_pair_test(){
+    assert_eq!(InfoTriple::canonical_pair("b".to_string(), "a".to_string()), ("a".to_string(), "b".to_string()));
+    assert_eq!(InfoTriple::canonical_pair("a".to_string(), "b".to_string()), ("a".to_string(), "b".to_string()));
+}
+
 #[test]
 fn to_one_string_test(){
     let triple = InfoTriple {
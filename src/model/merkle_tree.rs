@@ -0,0 +1,301 @@
+use std::fmt;
+use std::str::FromStr;
+
+use ig_tools::hashing_tools;
+
+///
+/// A Merkle tree over the triple ids of an info table. The leaves are the ids sorted and without
+/// duplicates, so the root only depends on which triples a table has, not on their order or on
+/// how often they were appended. Hashes are made with hashing_tools::concat_n_hash(), the same
+/// hash the triple ids are made with, under a different tag for leaves, nodes, the empty tree and
+/// the tables of a space, so no node can pass for a leaf or a table. An odd node at the end of a
+/// level is promoted to the next level as it is.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleTree {
+    ids: Vec<String>,
+    levels: Vec<Vec<String>>,
+}
+
+const LEAF_TAG: &str = "ig_tr leaf ";
+const NODE_TAG: &str = "ig_tr node ";
+const EMPTY_TAG: &str = "ig_tr empty";
+const TABLE_TAG: &str = "ig_tr table ";
+
+impl MerkleTree {
+
+    pub fn from_ids<I: IntoIterator<Item = String>>(ids: I) -> Self {
+        let mut leaves: Vec<String> = ids.into_iter().collect();
+        leaves.sort();
+        leaves.dedup();
+
+        let mut levels = vec![leaves.iter().map(|x| Self::leaf_hash(x)).collect::<Vec<String>>()];
+        while levels.last().unwrap().len() > 1 {
+            let next = Self::next_level(levels.last().unwrap());
+            levels.push(next);
+        }
+        MerkleTree { ids: leaves, levels }
+    }
+
+    ///
+    /// Builds the tree over the ids of the lines of a table as returned by
+    /// TripleStore::get_info_table().
+    ///
+    pub fn from_table_lines(lines: &str) -> Self {
+        MerkleTree::from_ids(lines
+            .lines()
+            .filter(|x| !x.is_empty())
+            .map(|x| x.split_once(' ').map_or(x, |(id, _)| id).to_string()))
+    }
+
+    ///
+    /// Returns the root hash. The root of an empty tree is the hash of the empty tag.
+    ///
+    pub fn root(&self) -> String {
+        match self.levels.last().unwrap().first() {
+            Some(root) => root.clone(),
+            None => Self::empty_root(),
+        }
+    }
+
+    ///
+    /// Returns the triple ids the leaves are made from, sorted.
+    ///
+    pub fn leaves(&self) -> &[String] {
+        &self.ids
+    }
+
+    ///
+    /// Returns the proof that triple_id is a leaf of the tree, or None if it is not.
+    ///
+    pub fn proof(&self, triple_id: &str) -> Option<MerkleProof> {
+        let leaf_index = self.ids.binary_search_by(|x| x.as_str().cmp(triple_id)).ok()?;
+        let mut index = leaf_index;
+        let mut steps: Vec<ProofStep> = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            //a promoted node has no sibling and no step
+            if index % 2 == 1 {
+                steps.push(ProofStep::Left(level[index - 1].clone()));
+            } else if index + 1 < level.len() {
+                steps.push(ProofStep::Right(level[index + 1].clone()));
+            }
+            index /= 2;
+        }
+        Some(MerkleProof { triple_id: triple_id.to_string(), leaf_index, tree_size: self.ids.len(), steps })
+    }
+
+    fn tagged_hash(tag: &str, data: &str) -> String {
+        let hashed = hashing_tools::concat_n_hash(tag, data);
+        hashed.split_once(' ').map_or(hashed.as_str(), |(id, _)| id).to_string()
+    }
+
+    pub fn leaf_hash(triple_id: &str) -> String {
+        Self::tagged_hash(LEAF_TAG, triple_id)
+    }
+
+    pub fn hash_pair(left: &str, right: &str) -> String {
+        Self::tagged_hash(NODE_TAG, &format!("{} {}", left, right))
+    }
+
+    pub fn empty_root() -> String {
+        Self::tagged_hash(EMPTY_TAG, "")
+    }
+
+    ///
+    /// Returns the leaf of a table in the space root. The name is prefixed with its length, so
+    /// no other name and root make the same leaf.
+    ///
+    pub fn table_hash(infotable_name: &str, root: &str) -> String {
+        Self::tagged_hash(TABLE_TAG, &format!("{}:{} {}", infotable_name.len(), infotable_name, root))
+    }
+
+    ///
+    /// Returns the root of a space from the roots of its tables. Every table is a leaf hashed
+    /// together with its name (see table_hash()), in name order, so renaming a table changes the
+    /// space root. Empty tables are left out, as a store may or may not keep them around.
+    ///
+    pub fn space_root<I: IntoIterator<Item = (String, MerkleTree)>>(tables: I) -> String {
+        let mut tables: Vec<(String, MerkleTree)> = tables
+            .into_iter()
+            .filter(|(_, tree)| !tree.leaves().is_empty())
+            .collect();
+        tables.sort_by(|a, b| a.0.cmp(&b.0));
+
+        //not MerkleTree::from_ids(), the leaves stay in name order
+        let mut level: Vec<String> = tables
+            .iter()
+            .map(|(name, tree)| Self::table_hash(name, &tree.root()))
+            .collect();
+        if level.is_empty() {
+            return Self::empty_root();
+        }
+        while level.len() > 1 {
+            level = Self::next_level(&level);
+        }
+        level.remove(0)
+    }
+
+    fn next_level(level: &[String]) -> Vec<String> {
+        level
+            .chunks(2)
+            .map(|x| match x {
+                [left, right] => Self::hash_pair(left, right),
+                [single] => single.clone(),
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+}
+
+///
+/// A sibling hash on the path from a leaf to the root, on the left or the right of the path.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProofStep {
+    Left(String),
+    Right(String),
+}
+
+///
+/// A Merkle inclusion proof that a triple id is in a table with a given root, without the rest of
+/// the table. The proof names the position of the leaf and the number of leaves, which fix how
+/// many steps there are and on which side each sibling is. Its text form is the triple id, the
+/// leaf index and tree size separated by a slash, and the steps from the leaf up, each an L or R
+/// for the side of the sibling directly followed by the sibling hash, all separated by spaces:
+///
+/// `<triple id> <leaf index>/<tree size> L<hash> R<hash> ...`
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleProof {
+    pub triple_id: String,
+    pub leaf_index: usize,
+    pub tree_size: usize,
+    pub steps: Vec<ProofStep>,
+}
+
+impl MerkleProof {
+
+    ///
+    /// Returns true if the steps have the shape of the path from leaf_index in a tree of
+    /// tree_size leaves and lead from the leaf of the triple id to root.
+    ///
+    pub fn verify(&self, root: &str) -> bool {
+        if self.leaf_index >= self.tree_size {
+            return false;
+        }
+        let mut steps = self.steps.iter();
+        let mut node = MerkleTree::leaf_hash(&self.triple_id);
+        let (mut index, mut size) = (self.leaf_index, self.tree_size);
+        while size > 1 {
+            //a promoted node has no sibling and no step
+            if index % 2 == 1 || index + 1 < size {
+                node = match (index % 2 == 1, steps.next()) {
+                    (true, Some(ProofStep::Left(sibling))) => MerkleTree::hash_pair(sibling, &node),
+                    (false, Some(ProofStep::Right(sibling))) => MerkleTree::hash_pair(&node, sibling),
+                    _ => return false,
+                };
+            }
+            index /= 2;
+            size = size.div_ceil(2);
+        }
+        steps.next().is_none() && node == root
+    }
+}
+
+impl fmt::Display for MerkleProof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}/{}", self.triple_id, self.leaf_index, self.tree_size)?;
+        for step in &self.steps {
+            match step {
+                ProofStep::Left(x) => write!(f, " L{}", x)?,
+                ProofStep::Right(x) => write!(f, " R{}", x)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for MerkleProof {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut fields = s.split_whitespace();
+        let triple_id = fields.next().ok_or("empty proof".to_string())?.to_string();
+        let position = fields.next().ok_or("proof without leaf index and tree size".to_string())?;
+        let (leaf_index, tree_size) = position
+            .split_once('/')
+            .and_then(|(index, size)| Some((index.parse::<usize>().ok()?, size.parse::<usize>().ok()?)))
+            .filter(|(index, size)| index < size)
+            .ok_or(format!("invalid leaf index and tree size {}", position))?;
+        let steps = fields
+            .map(|x| match x.split_at_checked(1) {
+                Some(("L", hash)) if !hash.is_empty() => Ok(ProofStep::Left(hash.to_string())),
+                Some(("R", hash)) if !hash.is_empty() => Ok(ProofStep::Right(hash.to_string())),
+                _ => Err(format!("invalid proof step {}", x)),
+            })
+            .collect::<Result<Vec<ProofStep>, String>>()?;
+        Ok(MerkleProof { triple_id, leaf_index, tree_size, steps })
+    }
+}
+
+///
+/// Checks a proof in text form against a published table root, so a third party needs neither
+/// the table nor a store.
+///
+pub fn verify_inclusion(proof: &str, root: &str) -> Result<bool, String> {
+    Ok(proof.parse::<MerkleProof>()?.verify(root))
+}
+
+
+#[test]
+fn merkle_root_test() {
+    let a = MerkleTree::from_ids(vec!["c".to_string(), "a".to_string(), "b".to_string()]);
+    let b = MerkleTree::from_table_lines("b id1 id2\na id1 id2\nc id1 id2\na id1 id2\n");
+    assert_eq!(a.root(), b.root());
+    let leaf = |x: &str| MerkleTree::leaf_hash(x);
+    assert_eq!(a.root(), MerkleTree::hash_pair(&MerkleTree::hash_pair(&leaf("a"), &leaf("b")), &leaf("c")));
+    assert_eq!(MerkleTree::from_ids(vec!["a".to_string()]).root(), leaf("a"));
+    assert_eq!(MerkleTree::from_table_lines("").root(), MerkleTree::empty_root());
+    assert_ne!(MerkleTree::from_table_lines("").root(), a.root());
+    //a node is not a leaf: the tree over a and b differs from a tree with the node as its id
+    let ab = MerkleTree::from_ids(vec!["a".to_string(), "b".to_string()]);
+    assert_ne!(MerkleTree::from_ids(vec![ab.root()]).root(), ab.root());
+
+    let space = MerkleTree::space_root(vec![("t1".to_string(), a.clone()), ("t2".to_string(), b.clone())]);
+    let reversed = MerkleTree::space_root(vec![("t2".to_string(), b), ("t1".to_string(), a.clone())]);
+    assert_eq!(space, reversed);
+    assert_ne!(space, MerkleTree::space_root(vec![("t3".to_string(), a.clone()), ("t2".to_string(), a)]));
+    assert_ne!(MerkleTree::table_hash("t1", "ab"), MerkleTree::table_hash("t1a", "b"));
+}
+
+#[test]
+fn inclusion_proof_test() {
+    let tree = MerkleTree::from_ids(["e", "b", "a", "d", "c"].map(|x| x.to_string()));
+    let root = tree.root();
+    for id in tree.leaves() {
+        let proof = tree.proof(id).unwrap();
+        assert!(proof.verify(&root));
+        assert_eq!(verify_inclusion(&proof.to_string(), &root), Ok(true));
+        assert!(!proof.verify(&MerkleTree::from_ids(vec!["a".to_string()]).root()));
+    }
+    //e is promoted twice, so its only step is the root of a to d
+    let abcd = MerkleTree::from_ids(["a", "b", "c", "d"].map(|x| x.to_string())).root();
+    assert_eq!(tree.proof("e").unwrap().to_string(), format!("e 4/5 L{}", abcd));
+    assert_eq!(tree.proof("x"), None);
+
+    let forged = tree.proof("a").unwrap().to_string().replacen("a", "x", 1);
+    assert_eq!(verify_inclusion(&forged, &root), Ok(false));
+    //the same steps at another position or in a tree of another size do not verify
+    assert_eq!(verify_inclusion(&format!("e 3/5 L{}", abcd), &root), Ok(false));
+    assert_eq!(verify_inclusion(&format!("e 4/6 L{}", abcd), &root), Ok(false));
+    assert_eq!(verify_inclusion(&format!("e 4/5 R{}", abcd), &root), Ok(false));
+    assert_eq!(verify_inclusion(&format!("e 4/5 L{} L{}", abcd, abcd), &root), Ok(false));
+    //a node of the tree cannot be proven as a leaf
+    let ab = MerkleTree::hash_pair(&MerkleTree::leaf_hash("a"), &MerkleTree::leaf_hash("b"));
+    let cd = MerkleTree::hash_pair(&MerkleTree::leaf_hash("c"), &MerkleTree::leaf_hash("d"));
+    assert_eq!(verify_inclusion(&format!("{} 0/3 R{} R{}", ab, cd, MerkleTree::leaf_hash("e")), &root), Ok(false));
+    assert!(verify_inclusion("a 0/5 X123", &root).is_err());
+    assert!(verify_inclusion("a L123", &root).is_err());
+    assert!(verify_inclusion("a 5/5", &root).is_err());
+    assert!(verify_inclusion("", &root).is_err());
+}
@@ -0,0 +1,204 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+///
+/// The triples added to and removed from one info table between two versions, as whole triple
+/// lines sorted by id.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TablePatch {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl TablePatch {
+
+    ///
+    /// Returns the patch turning the table lines old into new. Triples are compared by id, so
+    /// order and duplicates do not make a difference.
+    ///
+    pub fn between(old: &str, new: &str) -> Self {
+        let old_lines = Self::lines_by_id(old);
+        let new_lines = Self::lines_by_id(new);
+        let mut patch = TablePatch {
+            added: new_lines.iter().filter(|(id, _)| !old_lines.contains_key(*id)).map(|(_, x)| x.to_string()).collect(),
+            removed: old_lines.iter().filter(|(id, _)| !new_lines.contains_key(*id)).map(|(_, x)| x.to_string()).collect(),
+        };
+        patch.added.sort_by(|a, b| Self::id(a).cmp(Self::id(b)));
+        patch.removed.sort_by(|a, b| Self::id(a).cmp(Self::id(b)));
+        patch
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    ///
+    /// Returns what keeps the patch from applying to the table lines: added triples the table
+    /// already has and removed triples it does not have.
+    ///
+    pub fn conflicts(&self, infotable_name: &str, lines: &str) -> Vec<PatchConflict> {
+        let ids: HashSet<&str> = lines.lines().map(Self::id).collect();
+        let already_there = self.added
+            .iter()
+            .filter(|x| ids.contains(Self::id(x)))
+            .map(|x| PatchConflict::AlreadyThere { infotable_name: infotable_name.to_string(), triple_id: Self::id(x).to_string() });
+        let missing = self.removed
+            .iter()
+            .filter(|x| !ids.contains(Self::id(x)))
+            .map(|x| PatchConflict::Missing { infotable_name: infotable_name.to_string(), triple_id: Self::id(x).to_string() });
+        already_there.chain(missing).collect()
+    }
+
+    ///
+    /// Returns the table lines with the removed triples left out and the added ones at the end.
+    ///
+    pub fn apply_to(&self, lines: &str) -> String {
+        let removed: HashSet<&str> = self.removed.iter().map(|x| Self::id(x)).collect();
+        let mut patched = String::new();
+        lines
+            .lines()
+            .filter(|x| !removed.contains(Self::id(x)))
+            .chain(self.added.iter().map(|x| x.as_str()))
+            .for_each(|x|{
+                patched.push_str(x);
+                patched.push('\n');
+            });
+        patched
+    }
+
+    fn lines_by_id(lines: &str) -> HashMap<&str, &str> {
+        lines.lines().filter(|x| !x.is_empty()).map(|x| (Self::id(x), x)).collect()
+    }
+
+    fn id(line: &str) -> &str {
+        line.split_once(' ').map_or(line, |(id, _)| id)
+    }
+}
+
+///
+/// The changes between two versions of a space, per info table. Tables without changes are left
+/// out.
+///
+/// Its text form starts with a version line, followed by a `table` line per table (in name order)
+/// and the table's removed (`-`) and added (`+`) triples, each sorted by id:
+///
+/// ```text
+/// ig_tr patch 1
+/// table main_table
+/// - <id> <id1> <id2>
+/// + <id> <id1> <id2>
+/// ```
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SpacePatch {
+    pub tables: BTreeMap<String, TablePatch>,
+}
+
+impl SpacePatch {
+
+    pub const HEADER: &'static str = "ig_tr patch 1";
+
+    pub fn is_empty(&self) -> bool {
+        self.tables.values().all(|x| x.is_empty())
+    }
+
+    pub fn total_added(&self) -> usize {
+        self.tables.values().map(|x| x.added.len()).sum()
+    }
+
+    pub fn total_removed(&self) -> usize {
+        self.tables.values().map(|x| x.removed.len()).sum()
+    }
+}
+
+impl fmt::Display for SpacePatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", SpacePatch::HEADER)?;
+        for (table, patch) in self.tables.iter().filter(|(_, x)| !x.is_empty()) {
+            writeln!(f, "table {}", table)?;
+            for line in &patch.removed {
+                writeln!(f, "- {}", line)?;
+            }
+            for line in &patch.added {
+                writeln!(f, "+ {}", line)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SpacePatch {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut lines = s.lines();
+        if lines.next() != Some(SpacePatch::HEADER) {
+            return Err(format!("patch does not start with {}", SpacePatch::HEADER));
+        }
+        let mut patch = SpacePatch::default();
+        let mut table: Option<&mut TablePatch> = None;
+        for (number, line) in lines.enumerate().filter(|(_, x)| !x.is_empty()) {
+            match (line.split_at_checked(2), table.as_mut()) {
+                (Some(("- ", triple)), Some(x)) => x.removed.push(triple.to_string()),
+                (Some(("+ ", triple)), Some(x)) => x.added.push(triple.to_string()),
+                _ => match line.strip_prefix("table ") {
+                    Some(name) => table = Some(patch.tables.entry(name.to_string()).or_default()),
+                    None => return Err(format!("invalid patch line {}: {}", number + 2, line)),
+                },
+            }
+        }
+        Ok(patch)
+    }
+}
+
+///
+/// Why a patch could not be applied to a table.
+///
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PatchConflict {
+    AlreadyThere { infotable_name: String, triple_id: String },
+    Missing { infotable_name: String, triple_id: String },
+}
+
+impl fmt::Display for PatchConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PatchConflict::AlreadyThere { infotable_name, triple_id } =>
+                write!(f, "{} already has the added triple {}", infotable_name, triple_id),
+            PatchConflict::Missing { infotable_name, triple_id } =>
+                write!(f, "{} does not have the removed triple {}", infotable_name, triple_id),
+        }
+    }
+}
+
+
+#[test]
+fn table_patch_test() {
+    let old = "id-a id1-a id2-a\nid-b id1-b id2-b\nid-a id1-a id2-a\n";
+    let new = "id-c id1-c id2-c\nid-a id1-a id2-a\n";
+    let patch = TablePatch::between(old, new);
+    assert_eq!(patch.added, vec!["id-c id1-c id2-c".to_string()]);
+    assert_eq!(patch.removed, vec!["id-b id1-b id2-b".to_string()]);
+    assert_eq!(patch.apply_to(old), "id-a id1-a id2-a\nid-a id1-a id2-a\nid-c id1-c id2-c\n");
+    assert!(patch.conflicts("main_table", old).is_empty());
+    assert_eq!(patch.conflicts("main_table", new), vec![
+        PatchConflict::AlreadyThere { infotable_name: "main_table".to_string(), triple_id: "id-c".to_string() },
+        PatchConflict::Missing { infotable_name: "main_table".to_string(), triple_id: "id-b".to_string() },
+    ]);
+}
+
+#[test]
+fn space_patch_text_test() {
+    let mut patch = SpacePatch::default();
+    patch.tables.insert("main_table".to_string(), TablePatch::between("id-b id1-b id2-b\n", "id-c id1-c id2-c\nid-a id1-a id2-a\n"));
+    patch.tables.insert("empty_table".to_string(), TablePatch::default());
+    let text = patch.to_string();
+    assert_eq!(text, "ig_tr patch 1\ntable main_table\n- id-b id1-b id2-b\n+ id-a id1-a id2-a\n+ id-c id1-c id2-c\n");
+
+    patch.tables.remove("empty_table");
+    assert_eq!(text.parse::<SpacePatch>(), Ok(patch));
+    assert!("ig_tr patch 1\n+ id-a id1-a id2-a\n".parse::<SpacePatch>().is_err());
+    assert!("table main_table\n".parse::<SpacePatch>().is_err());
+}
@@ -0,0 +1,40 @@
+use std::collections::BTreeMap;
+
+///
+/// The number of triples a sync added to each side of one info table.
+///
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TableSync {
+    /// Triples only the other side had, added to this side.
+    pub added_here: usize,
+    /// Triples only this side had, added to the other side.
+    pub added_there: usize,
+}
+
+///
+/// The result of syncing two spaces with TripleFacade::sync_with(), per info table. With dry_run
+/// the counts are what a sync would add, and nothing was written.
+///
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SyncReport {
+    pub tables: BTreeMap<String, TableSync>,
+    pub dry_run: bool,
+}
+
+impl SyncReport {
+
+    pub fn total_added_here(&self) -> usize {
+        self.tables.values().map(|x| x.added_here).sum()
+    }
+
+    pub fn total_added_there(&self) -> usize {
+        self.tables.values().map(|x| x.added_there).sum()
+    }
+
+    ///
+    /// Returns true if both sides already had the same triples.
+    ///
+    pub fn was_in_sync(&self) -> bool {
+        self.total_added_here() == 0 && self.total_added_there() == 0
+    }
+}
@@ -0,0 +1,13 @@
+use std::time::SystemTime;
+
+///
+/// A version of an info table to read it as of (see TripleFacade::get_info_table_as_of()).
+///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableVersion {
+    /// The table with its first lines lines, as tables are append-only logs, as long as it is
+    /// still in generation (see TripleStore::table_generation()), i.e. not rewritten since.
+    Seq { generation: u64, lines: u64 },
+    /// The table as it was at a time, for stores recording append times.
+    Time(SystemTime),
+}
@@ -1,3 +1,23 @@
 pub mod triple_facade; 
 pub mod triple_store;
 pub mod triple_store_fs;
+pub mod table_file;
+pub mod table_format;
+pub mod table_compression;
+pub mod table_encryption;
+pub mod store_error;
+pub mod cached_store;
+pub mod overlay_store;
+#[cfg(test)]
+mod store_test_suite;
+#[cfg(feature = "sqlite")]
+pub mod triple_store_sqlite;
+#[cfg(feature = "kv")]
+pub mod triple_store_kv;
+
+#[cfg(feature = "async")]
+pub mod async_triple_store;
+#[cfg(feature = "async")]
+pub mod async_triple_store_fs;
+#[cfg(feature = "async")]
+pub mod async_triple_facade;
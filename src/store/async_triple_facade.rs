@@ -0,0 +1,72 @@
+use crate::{InfoTriple, InfoTable};
+
+use super::async_triple_store::AsyncTripleStore;
+
+
+///
+/// The async version of TripleFacade.
+///
+#[derive(Clone)]
+pub struct AsyncTripleFacade<T:AsyncTripleStore> {
+    pub storage: T,
+}
+
+impl<T:AsyncTripleStore> AsyncTripleFacade<T> {
+
+    pub fn new(storage: T) -> Self{
+        AsyncTripleFacade {storage}
+    }
+
+    pub async fn add_to_infotable(&mut self, infotable_name: String, infotriple: InfoTriple) {
+        self.append_info_table(&infotable_name, &infotriple.to_tuple_string()).await;
+    }
+
+    pub async fn append_info_table(&mut self, infotable_name: &str, infotriple: &str) {
+        self.storage.append_info_table(infotable_name, infotriple).await;
+    }
+
+    pub async fn add_many_to_infotable(&mut self, infotable_name: String, infotriples: Vec<InfoTriple>) {
+        self.storage.append_many(
+            &infotable_name,
+            infotriples.iter().map(|x| x.to_tuple_string()).collect(),
+        ).await;
+    }
+
+    pub async fn get_all_info_triples_from_info_table(&mut self, infotable_name: &str) -> Vec<InfoTriple> {
+        self.storage.get_info_table(infotable_name)
+            .await
+            .lines()
+            .map(|x|InfoTriple::from(x.to_string()))
+            .collect()
+    }
+
+    pub async fn get_info_table_as_info_table(&mut self, infotable_name: &str) -> InfoTable {
+        self.storage.get_info_table(infotable_name)
+            .await
+            .lines()
+            .map(|x|InfoTriple::from(x.to_string()))
+            .collect()
+    }
+
+    pub async fn select_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> Vec<InfoTriple> {
+        self.storage.select_from_info_table(infotable_name, where_id)
+            .await
+            .lines()
+            .map(|x|InfoTriple::from(x.to_string()))
+            .collect()
+    }
+
+    pub async fn get_triple(&mut self, infotable_name: &str, triple_id: &str) -> Option<InfoTriple> {
+        self.storage.get_triple(infotable_name, triple_id)
+            .await
+            .map(InfoTriple::from)
+    }
+
+    pub async fn contains_triple(&mut self, infotable_name: &str, triple_id: &str) -> bool {
+        self.storage.contains_triple(infotable_name, triple_id).await
+    }
+
+    pub async fn clear_infotable(&mut self, infotable_name: String) {
+        self.storage.clear_infotable(infotable_name).await;
+    }
+}
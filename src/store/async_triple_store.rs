@@ -0,0 +1,31 @@
+use std::future::Future;
+
+///
+/// The async counterpart of TripleStore, for stores used from async services where blocking IO
+/// on the executor threads is not an option.
+///
+pub trait AsyncTripleStore {
+
+    fn append_info_table(&mut self, infotable_name: &str, infotriple: &str) -> impl Future<Output = ()> + Send;
+
+    fn append_many(&mut self, infotable_name: &str, infotriples: Vec<String>) -> impl Future<Output = ()> + Send;
+
+    fn get_info_table(&mut self, infotable_name: &str) -> impl Future<Output = String> + Send;
+
+    ///
+    /// Returns the lines of the triples whose id1 or id2 is exactly where_id, in append order.
+    ///
+    fn select_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> impl Future<Output = String> + Send;
+
+    fn get_triple(&mut self, infotable_name: &str, triple_id: &str) -> impl Future<Output = Option<String>> + Send;
+
+    fn contains_triple(&mut self, infotable_name: &str, triple_id: &str) -> impl Future<Output = bool> + Send;
+
+    fn clear_infotable(&mut self, infotable_name: String) -> impl Future<Output = ()> + Send;
+
+    fn set_tmp_space_id(&mut self, space_id: String);
+
+    fn revert_space_id(&mut self);
+
+    fn get_space_id(&mut self) -> String;
+}
@@ -0,0 +1,200 @@
+use tokio::fs;
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use super::async_triple_store::AsyncTripleStore;
+use super::table_file::TableLock;
+use super::table_compression;
+use super::table_encryption;
+use super::table_format::{self, TableFormat};
+use super::triple_store::TripleStore;
+use super::triple_store_fs::TripleStoreFS;
+
+///
+/// The async version of TripleStoreFS, working on the same files with the same locks, so both
+/// can be used on one infospace at the same time. Table content is read with tokio's non-blocking
+/// file IO, while writes (through the same table_file functions as TripleStoreFS), taking locks,
+/// the partial line check and decoding compressed or encrypted tables run on tokio's blocking
+/// thread pool.
+///
+#[derive(Clone)]
+pub struct AsyncTripleStoreFS {
+    store: TripleStoreFS,
+}
+
+impl AsyncTripleStoreFS {
+    pub fn new(space_id: String) -> Self {
+        AsyncTripleStoreFS::from_store(TripleStoreFS::new(space_id))
+    }
+
+    ///
+    /// Creates an async store with the settings (sync policy, lock timeout) of a TripleStoreFS.
+    ///
+    pub fn from_store(store: TripleStoreFS) -> Self {
+        AsyncTripleStoreFS { store }
+    }
+
+    ///
+    /// Runs f with the inner TripleStoreFS on the blocking thread pool and takes its state back
+    /// afterwards.
+    ///
+    async fn blocking<T, F>(&mut self, f: F) -> T
+    where
+        T: Send + 'static,
+        F: FnOnce(&mut TripleStoreFS) -> T + Send + 'static,
+    {
+        let mut store = self.store.clone();
+        let (store, result) = tokio::task::spawn_blocking(move || {
+            let result = f(&mut store);
+            (store, result)
+        })
+        .await
+        .expect("something went wrong in a blocking triple store task");
+        self.store = store;
+        result
+    }
+
+    ///
+    /// Creates the table file if needed and returns its path.
+    ///
+    async fn prepare_table(&mut self, table_name: &str) -> String {
+        let table_name = table_name.to_string();
+        self.blocking(move |store| {
+            store.create_tablefile_if_not_there(table_name.clone());
+            store.get_table_path(table_name)
+        }).await
+    }
+
+    async fn lock_table(&mut self, table_name: &str, exclusive: bool) -> TableLock {
+        let table_name = table_name.to_string();
+        self.blocking(move |store| store.lock_table(table_name, exclusive).unwrap_or_else(|e| panic!("{}", e))).await
+    }
+
+    async fn decode_table_file(&mut self, table_name: &str, content: Vec<u8>) -> String {
+        let table_name = table_name.to_string();
+        self.blocking(move |store| store.decode_table_file(&table_name, content))
+            .await
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+}
+
+impl AsyncTripleStore for AsyncTripleStoreFS {
+
+    //temporary space handling begin
+    fn set_tmp_space_id(&mut self, space_id: String) {
+        self.store.set_tmp_space_id(space_id);
+    }
+
+    fn revert_space_id(&mut self) {
+        self.store.revert_space_id();
+    }
+
+    fn get_space_id(&mut self) -> String {
+        self.store.get_space_id()
+    }
+    //temporary space handling end
+
+
+    async fn append_info_table(&mut self, infotable_name: &str, infotriple: &str) {
+        self.append_many(infotable_name, vec![infotriple.to_string()]).await;
+    }
+
+    async fn append_many(&mut self, infotable_name: &str, infotriples: Vec<String>) {
+        let table_name = infotable_name.to_string();
+        self.blocking(move |store| store.try_append_many(&table_name, infotriples))
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    async fn get_info_table(&mut self, infotable_name: &str) -> String {
+        let path = self.prepare_table(infotable_name).await;
+        let _lock = self.lock_table(infotable_name, false).await;
+        let content = fs::read(&path)
+            .await
+            .expect("something went wrong reading info table file");
+        self.decode_table_file(infotable_name, content).await
+    }
+
+    async fn select_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> String {
+        self.get_info_table(infotable_name)
+            .await
+            .lines()
+            .filter(|x| {
+                x.split(' ')
+                .skip(1)
+                .any(|id| id == where_id)
+            })
+            .fold("".to_string(),
+                |acc, y|
+                format!( "{}{}{}", acc, y, "\n"))
+    }
+
+    async fn get_triple(&mut self, infotable_name: &str, triple_id: &str) -> Option<String> {
+        let path = self.prepare_table(infotable_name).await;
+        let _lock = self.lock_table(infotable_name, false).await;
+
+        //stream the lines and stop at the first match instead of reading the whole table
+        let file = fs::File::open(&path)
+            .await
+            .expect("something went wrong opening info table file");
+        let mut reader = BufReader::new(file);
+        let head = reader.fill_buf().await.expect("something went wrong reading info table file");
+        if TableFormat::detect(head) == TableFormat::Binary {
+            //compare the records without decoding the table, on the blocking pool as it reads
+            //with std's file IO
+            let triple_id = triple_id.to_string();
+            return self.blocking(move |_| {
+                let file = std::fs::File::open(&path).expect("something went wrong opening info table file");
+                table_format::find_record(&mut std::io::BufReader::new(file), &triple_id)
+            })
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+        }
+        if table_compression::is_compressed(head) || table_encryption::is_encrypted(head) {
+            let content = fs::read(&path)
+                .await
+                .expect("something went wrong reading info table file");
+            return self.decode_table_file(infotable_name, content)
+                .await
+                .lines()
+                .find(|x| x.split_once(' ').is_some_and(|(id, _)| id == triple_id))
+                .map(|x| x.to_string());
+        }
+        let mut lines = reader.lines();
+        while let Some(x) = lines.next_line().await.expect("something went wrong reading info table text file") {
+            if x.split_once(' ').is_some_and(|(id, _)| id == triple_id) {
+                return Some(x);
+            }
+        }
+        None
+    }
+
+    async fn contains_triple(&mut self, infotable_name: &str, triple_id: &str) -> bool {
+        self.get_triple(infotable_name, triple_id).await.is_some()
+    }
+
+    async fn clear_infotable(&mut self, infotable_name: String) {
+        self.blocking(move |store| store.try_clear_infotable(infotable_name))
+            .await
+            .unwrap_or_else(|e| panic!("{}", e));
+    }
+}
+
+
+#[tokio::test]
+async fn async_append_and_get_test() {
+
+    let infotable_name = String::from("async_table");
+    let mut ts = AsyncTripleStoreFS::new("async_test_space".to_string());
+    ts.clear_infotable(infotable_name.clone()).await;
+    ts.append_info_table(&infotable_name, "id-a id1-a id2-a").await;
+    ts.append_many(&infotable_name, vec!["id-b id-a id2-b".to_string()]).await;
+
+    assert_eq!(ts.get_info_table(&infotable_name).await, "id-a id1-a id2-a\nid-b id-a id2-b\n".to_string());
+    assert_eq!(ts.select_from_info_table(&infotable_name, "id-a").await, "id-b id-a id2-b\n".to_string());
+    assert_eq!(ts.get_triple(&infotable_name, "id-a").await, Some("id-a id1-a id2-a".to_string()));
+    assert!(!ts.contains_triple(&infotable_name, "id1-a").await);
+
+    //the sync store sees the same table
+    let mut sync_ts = TripleStoreFS::new("async_test_space".to_string());
+    assert!(sync_ts.contains_triple(&infotable_name, "id-b"));
+}
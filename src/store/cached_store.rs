@@ -0,0 +1,239 @@
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use super::triple_store::{TableStamp, TripleStore};
+
+///
+/// A cached table: its content, the select results on it and the table's stamp when it was
+/// read.
+///
+struct CacheEntry {
+    content: Option<String>,
+    selects: HashMap<String, String>,
+    stamp: Option<TableStamp>,
+    bytes: usize,
+    last_used: u64,
+}
+
+///
+/// A TripleStore wrapping another one and caching table contents and select results per
+/// (space, table), so repeated reads of a table (like the ones in
+/// TripleFacade::get_n_flatten_all_from_table_list()) do not go to the store each time.
+///
+/// Writes through the wrapper drop the cached table. Writes by others are noticed through
+/// TripleStore::table_stamp(), so for stores that cannot tell (returning None) only writes
+/// through the wrapper invalidate. When the cached content grows over max_bytes the least
+/// recently used tables are dropped.
+///
+pub struct CachedStore<S: TripleStore> {
+    store: S,
+    entries: HashMap<(String, String), CacheEntry>,
+    max_bytes: usize,
+    bytes: usize,
+    tick: u64,
+}
+
+impl<S: TripleStore> CachedStore<S> {
+    pub fn new(store: S, max_bytes: usize) -> Self {
+        CachedStore {
+            store,
+            entries: HashMap::new(),
+            max_bytes,
+            bytes: 0,
+            tick: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> S {
+        self.store
+    }
+
+    ///
+    /// Returns the size of the cached table contents and select results in bytes.
+    ///
+    pub fn cached_bytes(&self) -> usize {
+        self.bytes
+    }
+
+    pub fn clear_cache(&mut self) {
+        self.entries.clear();
+        self.bytes = 0;
+    }
+
+    fn key(&mut self, infotable_name: &str) -> (String, String) {
+        (self.store.get_space_id(), infotable_name.to_string())
+    }
+
+    fn invalidate(&mut self, infotable_name: &str) {
+        let key = self.key(infotable_name);
+        if let Some(entry) = self.entries.remove(&key) {
+            self.bytes -= entry.bytes;
+        }
+    }
+
+    ///
+    /// Returns the entry for the table, dropping a cached one the store has written since.
+    ///
+    fn entry(&mut self, infotable_name: &str) -> &mut CacheEntry {
+        let key = self.key(infotable_name);
+        let stamp = self.store.table_stamp(infotable_name);
+        if self.entries.get(&key).is_some_and(|x| x.stamp != stamp) {
+            self.invalidate(infotable_name);
+        }
+
+        self.tick += 1;
+        let entry = self.entries.entry(key).or_insert(CacheEntry {
+            content: None,
+            selects: HashMap::new(),
+            stamp,
+            bytes: 0,
+            last_used: 0,
+        });
+        entry.last_used = self.tick;
+        entry
+    }
+
+    fn add_bytes(&mut self, infotable_name: &str, bytes: usize) {
+        self.entry(infotable_name).bytes += bytes;
+        self.bytes += bytes;
+        self.evict();
+    }
+
+    ///
+    /// Drops least recently used tables until the cache fits in max_bytes again.
+    ///
+    fn evict(&mut self) {
+        while self.bytes > self.max_bytes {
+            let oldest = self.entries
+                .iter()
+                .min_by_key(|(_, x)| x.last_used)
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some(key) => {
+                    let entry = self.entries.remove(&key).unwrap();
+                    self.bytes -= entry.bytes;
+                },
+                None => break,
+            }
+        }
+    }
+}
+
+impl<S: TripleStore> TripleStore for CachedStore<S> {
+
+    //temporary space handling begin
+    fn set_tmp_space_id(&mut self, space_id: String) {
+        self.store.set_tmp_space_id(space_id);
+    }
+
+    fn revert_space_id(&mut self) {
+        self.store.revert_space_id();
+    }
+
+    fn get_space_id(&mut self) -> String {
+        self.store.get_space_id()
+    }
+    //temporary space handling end
+
+
+    fn append_info_table(&mut self, infotable_name: &str, infotriple: &str) {
+        self.store.append_info_table(infotable_name, infotriple);
+        self.invalidate(infotable_name);
+    }
+
+    fn append_many<I: IntoIterator<Item = String>>(&mut self, infotable_name: &str, infotriples: I) {
+        self.store.append_many(infotable_name, infotriples);
+        self.invalidate(infotable_name);
+    }
+
+    fn get_info_table(&mut self, infotable_name: &str) -> String {
+        if let Some(content) = &self.entry(infotable_name).content {
+            return content.clone();
+        }
+        let content = self.store.get_info_table(infotable_name);
+        self.entry(infotable_name).content = Some(content.clone());
+        self.add_bytes(infotable_name, content.len());
+        content
+    }
+
+    fn select_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> String {
+        if let Some(selected) = self.entry(infotable_name).selects.get(where_id) {
+            return selected.clone();
+        }
+        let selected = self.store.select_from_info_table(infotable_name, where_id);
+        self.entry(infotable_name).selects.insert(where_id.to_string(), selected.clone());
+        self.add_bytes(infotable_name, where_id.len() + selected.len());
+        selected
+    }
+
+    fn get_triple(&mut self, infotable_name: &str, triple_id: &str) -> Option<String> {
+        match &self.entry(infotable_name).content {
+            Some(content) => content
+                .lines()
+                .find(|x| x.split_once(' ').is_some_and(|(id, _)| id == triple_id))
+                .map(|x| x.to_string()),
+            None => self.store.get_triple(infotable_name, triple_id),
+        }
+    }
+
+    fn clear_infotable(&mut self, infotable_name: String) {
+        self.store.clear_infotable(infotable_name.clone());
+        self.invalidate(&infotable_name);
+    }
+
+    fn replace_info_table(&mut self, infotable_name: &str, infotriples: Vec<String>) {
+        self.store.replace_info_table(infotable_name, infotriples);
+        self.invalidate(infotable_name);
+    }
+
+    fn list_info_tables(&mut self) -> Vec<String> {
+        self.store.list_info_tables()
+    }
+
+    fn table_stamp(&mut self, infotable_name: &str) -> Option<TableStamp> {
+        self.store.table_stamp(infotable_name)
+    }
+
+    fn table_generation(&mut self, infotable_name: &str) -> Option<u64> {
+        self.store.table_generation(infotable_name)
+    }
+
+    fn version_at(&mut self, infotable_name: &str, time: SystemTime) -> Option<u64> {
+        self.store.version_at(infotable_name, time)
+    }
+}
+
+
+#[test]
+fn store_test_suite_cached_test() {
+    let ts = super::triple_store_fs::TripleStoreFS::new("store_test_suite_cached_space".to_string());
+    super::store_test_suite::run_all(&mut CachedStore::new(ts, 1024 * 1024));
+}
+
+#[test]
+fn invalidation_and_eviction_test() {
+    use super::triple_store_fs::TripleStoreFS;
+
+    let mut other = TripleStoreFS::new("cached_test_space".to_string());
+    let mut ts = CachedStore::new(TripleStoreFS::new("cached_test_space".to_string()), 100);
+    ts.clear_infotable("main_table".to_string());
+    ts.append_info_table("main_table", "id-a id1-a id2-a");
+    assert_eq!(ts.get_info_table("main_table"), "id-a id1-a id2-a\n");
+    assert_eq!(ts.cached_bytes(), 17);
+
+    //a write by another store is noticed through the table's stamp, also within the same
+    //modification time
+    other.append_info_table("main_table", "id-b id1-b id2-b");
+    assert_eq!(ts.get_info_table("main_table"), "id-a id1-a id2-a\nid-b id1-b id2-b\n");
+    assert_eq!(ts.select_from_info_table("main_table", "id1-b"), "id-b id1-b id2-b\n");
+    other.clear_infotable("main_table".to_string());
+    other.append_info_table("main_table", "id-c id1-c id2-c");
+    other.append_info_table("main_table", "id-d id1-d id2-d");
+    assert_eq!(ts.get_info_table("main_table"), "id-c id1-c id2-c\nid-d id1-d id2-d\n");
+
+    //a table bigger than the bound is not kept
+    ts.clear_infotable("big_table".to_string());
+    ts.append_many("big_table", (0..10).map(|i| format!("id-{} id1-{} id2-{}", i, i, i)));
+    ts.get_info_table("big_table");
+    assert!(ts.cached_bytes() <= 100);
+}
@@ -0,0 +1,305 @@
+use std::collections::{BTreeSet, HashSet};
+use std::time::SystemTime;
+
+use super::triple_store::{TableStamp, TripleStore};
+
+///
+/// A TripleStore showing a writable store (top) over a read-only one (base), e.g. a private space
+/// of a user over a shared base space. Reads return the base triples followed by the top ones,
+/// writes only go to top, and removed triples are recorded as tombstones in a companion table of
+/// top (see tombstone_table()), so base is never written.
+///
+/// Switching the space (set_tmp_space_id()) only switches top, base always stays in its space.
+///
+pub struct OverlayStore<Base: TripleStore, Top: TripleStore> {
+    base: Base,
+    top: Top,
+}
+
+impl<Base: TripleStore, Top: TripleStore> OverlayStore<Base, Top> {
+    pub fn new(base: Base, top: Top) -> Self {
+        OverlayStore { base, top }
+    }
+
+    pub fn into_inner(self) -> (Base, Top) {
+        (self.base, self.top)
+    }
+
+    ///
+    /// Returns the name of the table in top holding the tombstones of an info table, as whole
+    /// triple lines so they can be stored in any format.
+    ///
+    pub fn tombstone_table(infotable_name: &str) -> String {
+        format!("{}.tombstones", infotable_name)
+    }
+
+    ///
+    /// Panics for names ending like tombstone tables, as such a table of base would be mixed up
+    /// with the tombstones of another table in top.
+    ///
+    fn check_table_name(infotable_name: &str) {
+        if infotable_name.ends_with(".tombstones") {
+            panic!("{} cannot be used through an overlay, names ending in .tombstones are kept for tombstones", infotable_name);
+        }
+    }
+
+    ///
+    /// Hides the triple from reads of the table, whether it is in base or in top.
+    ///
+    pub fn remove_triple(&mut self, infotable_name: &str, triple_id: &str) {
+        Self::check_table_name(infotable_name);
+        if self.tombstones(infotable_name).contains(triple_id) {
+            return;
+        }
+        if let Some(triple) = self.get_triple(infotable_name, triple_id) {
+            self.top.append_info_table(&Self::tombstone_table(infotable_name), &triple);
+        }
+    }
+
+    fn tombstones(&mut self, infotable_name: &str) -> HashSet<String> {
+        self.top.get_info_table(&Self::tombstone_table(infotable_name))
+            .lines()
+            .map(|x| x.split_once(' ').map_or(x, |(id, _)| id).to_string())
+            .collect()
+    }
+
+    ///
+    /// Takes the ids of the triples being written again out of the tombstones, so they show up.
+    ///
+    fn revive(&mut self, infotable_name: &str, triple_ids: &HashSet<&str>) {
+        let tombstone_table = Self::tombstone_table(infotable_name);
+        let tombstones = self.top.get_info_table(&tombstone_table);
+        if !tombstones.lines().any(|x| triple_ids.contains(x.split_once(' ').map_or(x, |(id, _)| id))) {
+            return;
+        }
+        let remaining: Vec<String> = tombstones
+            .lines()
+            .filter(|x| !triple_ids.contains(x.split_once(' ').map_or(*x, |(id, _)| id)))
+            .map(|x| x.to_string())
+            .collect();
+        self.top.replace_info_table(&tombstone_table, remaining);
+    }
+
+    ///
+    /// Merges base and top lines, leaving out tombstoned triples. Like in any store, a triple
+    /// written to top that is in base already is kept as another line.
+    ///
+    fn merge(&mut self, infotable_name: &str, base_lines: String, top_lines: String) -> String {
+        let tombstones = self.tombstones(infotable_name);
+        let mut lines = String::new();
+        base_lines
+            .lines()
+            .chain(top_lines.lines())
+            .filter(|x| !tombstones.contains(x.split_once(' ').map_or(*x, |(id, _)| id)))
+            .for_each(|x|{
+                lines.push_str(x);
+                lines.push('\n');
+            });
+        lines
+    }
+}
+
+impl<Base: TripleStore, Top: TripleStore> TripleStore for OverlayStore<Base, Top> {
+
+    //temporary space handling begin
+    fn set_tmp_space_id(&mut self, space_id: String) {
+        self.top.set_tmp_space_id(space_id);
+    }
+
+    fn revert_space_id(&mut self) {
+        self.top.revert_space_id();
+    }
+
+    fn get_space_id(&mut self) -> String {
+        self.top.get_space_id()
+    }
+    //temporary space handling end
+
+
+    fn append_info_table(&mut self, infotable_name: &str, infotriple: &str) {
+        Self::check_table_name(infotable_name);
+        self.revive(infotable_name, &HashSet::from([infotriple.split_once(' ').map_or(infotriple, |(id, _)| id)]));
+        self.top.append_info_table(infotable_name, infotriple);
+    }
+
+    fn append_many<I: IntoIterator<Item = String>>(&mut self, infotable_name: &str, infotriples: I) {
+        Self::check_table_name(infotable_name);
+        let infotriples: Vec<String> = infotriples.into_iter().collect();
+        let ids: HashSet<&str> = infotriples
+            .iter()
+            .map(|x| x.split_once(' ').map_or(x.as_str(), |(id, _)| id))
+            .collect();
+        self.revive(infotable_name, &ids);
+        self.top.append_many(infotable_name, infotriples);
+    }
+
+    fn get_info_table(&mut self, infotable_name: &str) -> String {
+        let base_lines = self.base.get_info_table(infotable_name);
+        let top_lines = self.top.get_info_table(infotable_name);
+        self.merge(infotable_name, base_lines, top_lines)
+    }
+
+    fn select_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> String {
+        let base_lines = self.base.select_from_info_table(infotable_name, where_id);
+        let top_lines = self.top.select_from_info_table(infotable_name, where_id);
+        self.merge(infotable_name, base_lines, top_lines)
+    }
+
+    fn get_triple(&mut self, infotable_name: &str, triple_id: &str) -> Option<String> {
+        if self.tombstones(infotable_name).contains(triple_id) {
+            return None;
+        }
+        self.base.get_triple(infotable_name, triple_id)
+            .or_else(|| self.top.get_triple(infotable_name, triple_id))
+    }
+
+    ///
+    /// Clears the table in top and tombstones all of its base triples.
+    ///
+    fn clear_infotable(&mut self, infotable_name: String) {
+        self.replace_info_table(&infotable_name, Vec::new());
+    }
+
+    ///
+    /// Tombstones the base triples that are not in infotriples and replaces the table in top
+    /// with the rest of infotriples, so the base triples kept come first. The tombstones are
+    /// written first, so an interruption leaves the old top triples over fewer base triples
+    /// rather than base triples over the new ones.
+    ///
+    fn replace_info_table(&mut self, infotable_name: &str, infotriples: Vec<String>) {
+        Self::check_table_name(infotable_name);
+        let ids: HashSet<&str> = infotriples
+            .iter()
+            .map(|x| x.split_once(' ').map_or(x.as_str(), |(id, _)| id))
+            .collect();
+        let base_lines = self.base.get_info_table(infotable_name);
+        let (kept, tombstones): (Vec<&str>, Vec<&str>) = base_lines
+            .lines()
+            .partition(|x| ids.contains(x.split_once(' ').map_or(*x, |(id, _)| id)));
+        self.top.replace_info_table(&Self::tombstone_table(infotable_name), tombstones.iter().map(|x| x.to_string()).collect());
+
+        //the base lines kept stand for one occurrence of the same line each
+        let mut kept: Vec<&str> = kept;
+        let top_lines: Vec<String> = infotriples
+            .iter()
+            .filter(|x| match kept.iter().position(|y| y == x) {
+                Some(i) => {
+                    kept.swap_remove(i);
+                    false
+                },
+                None => true,
+            })
+            .cloned()
+            .collect();
+        self.top.replace_info_table(infotable_name, top_lines);
+    }
+
+    ///
+    /// Lists the tables of base and top, leaving out the tombstone tables.
+    ///
+    fn list_info_tables(&mut self) -> Vec<String> {
+        self.base.list_info_tables()
+            .into_iter()
+            .chain(self.top.list_info_tables())
+            .filter(|x| !x.ends_with(".tombstones"))
+            .collect::<BTreeSet<String>>()
+            .into_iter()
+            .collect()
+    }
+
+    fn table_stamp(&mut self, infotable_name: &str) -> Option<TableStamp> {
+        TableStamp::join([
+            self.base.table_stamp(infotable_name),
+            self.top.table_stamp(infotable_name),
+            self.top.table_stamp(&Self::tombstone_table(infotable_name)),
+        ])
+    }
+
+    ///
+    /// Adds the generations of base, top and the tombstones, which grows whenever one of them is
+    /// rewritten.
+    ///
+    fn table_generation(&mut self, infotable_name: &str) -> Option<u64> {
+        Some(self.base.table_generation(infotable_name)?
+            + self.top.table_generation(infotable_name)?
+            + self.top.table_generation(&Self::tombstone_table(infotable_name))?)
+    }
+
+    ///
+    /// Adds the lines base and top had at time, as long as that is the start of the merged
+    /// table: not with tombstones, which take lines out of the middle, and not when base has
+    /// grown since time under lines top had then.
+    ///
+    fn version_at(&mut self, infotable_name: &str, time: SystemTime) -> Option<u64> {
+        if !self.tombstones(infotable_name).is_empty() {
+            return None;
+        }
+        let base_version = self.base.version_at(infotable_name, time)?;
+        let top_version = self.top.version_at(infotable_name, time)?;
+        let base_lines = self.base.get_info_table(infotable_name).lines().count() as u64;
+        if top_version > 0 && base_version < base_lines {
+            return None;
+        }
+        Some(base_version + top_version)
+    }
+}
+
+
+#[test]
+fn store_test_suite_overlay_test() {
+    use super::triple_store_fs::TripleStoreFS;
+
+    let base = TripleStoreFS::new("store_test_suite_overlay_base_space".to_string());
+    let top = TripleStoreFS::new("store_test_suite_overlay_space".to_string());
+    super::store_test_suite::run_all(&mut OverlayStore::new(base, top));
+}
+
+#[test]
+fn overlay_test() {
+    use super::triple_store_fs::TripleStoreFS;
+    use crate::triple_facade::TripleFacade;
+    use crate::tr_director::TrDirector;
+
+    let mut base = TripleStoreFS::new("overlay_base_test_space".to_string());
+    base.clear_infotable("main_table".to_string());
+    base.append_many("main_table", vec!["id-a id1-a id-x".to_string(), "id-b id1-b id2-b".to_string()]);
+    let mut top = TripleStoreFS::new("overlay_test_space".to_string());
+    top.clear_infotable("main_table".to_string());
+    top.clear_infotable(OverlayStore::<TripleStoreFS, TripleStoreFS>::tombstone_table("main_table"));
+
+    let mut overlay = OverlayStore::new(base, top);
+    overlay.append_info_table("main_table", "id-c id1-c id-x");
+    overlay.remove_triple("main_table", "id-b");
+    assert_eq!(overlay.get_info_table("main_table"), "id-a id1-a id-x\nid-c id1-c id-x\n");
+    assert_eq!(overlay.select_from_info_table("main_table", "id-x"), "id-a id1-a id-x\nid-c id1-c id-x\n");
+    assert_eq!(overlay.get_triple("main_table", "id-b"), None);
+
+    //written again, a removed triple shows up again
+    overlay.append_info_table("main_table", "id-b id1-b id2-b");
+    assert!(overlay.contains_triple("main_table", "id-b"));
+
+    //base is never written
+    let (mut base, top) = overlay.into_inner();
+    assert_eq!(base.get_info_table("main_table"), "id-a id1-a id-x\nid-b id1-b id2-b\n");
+
+    let mut director = TrDirector::new(TripleFacade::new(OverlayStore::new(base, top)));
+    let it = director.create_triple("id1-d".to_string(), "id2-d".to_string());
+    //id-b is in base and was written again to top
+    assert_eq!(director.get_all_info_triples_from_info_table("main_table".to_string()).len(), 5);
+    assert!(director.find_triple("id1-d".to_string(), "id2-d".to_string()) == Some(it));
+
+    //replacing keeps the base triples written again and tombstones the others
+    let overlay = &mut director.triples.storage;
+    overlay.replace_info_table("main_table", vec!["id-e id1-e id2-e".to_string(), "id-a id1-a id-x".to_string()]);
+    assert_eq!(overlay.get_info_table("main_table"), "id-a id1-a id-x\nid-e id1-e id2-e\n");
+}
+
+#[test]
+#[should_panic(expected = "kept for tombstones")]
+fn overlay_tombstone_name_test() {
+    use super::triple_store_fs::TripleStoreFS;
+
+    let base = TripleStoreFS::new("overlay_base_test_space".to_string());
+    let top = TripleStoreFS::new("overlay_test_space".to_string());
+    OverlayStore::new(base, top).append_info_table("main_table.tombstones", "id-a id1-a id2-a");
+}
@@ -0,0 +1,65 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::time::Duration;
+
+use crate::PatchConflict;
+
+///
+/// Errors from the storage level that callers may want to handle, rather than just the generic
+/// "something went wrong" panics.
+///
+#[derive(Debug)]
+pub enum StoreError {
+    /// Another process (or handle) held a conflicting lock on the table for longer than the
+    /// lock timeout.
+    LockTimeout { path: String, exclusive: bool, timeout: Duration },
+    /// Table content that does not fit its format, e.g. an id that is no hex hash in a binary
+    /// table.
+    Format(String),
+    /// The space is encrypted and no key for it was added to the store.
+    MissingKey { space_id: String },
+    /// The key added for the space is not the key the file was encrypted with.
+    WrongKey { path: String },
+    /// An encrypted table file is shorter than its length commitment or ends with a partial
+    /// frame, so triples have been cut off.
+    Truncated { path: String },
+    /// A patch does not fit the tables it is applied to; nothing of it was written.
+    PatchConflicts(Vec<PatchConflict>),
+    Io(io::Error),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::LockTimeout { path, exclusive, timeout } => write!(f,
+                "timed out after {:?} waiting for {} lock on {}, another process is holding a conflicting lock",
+                timeout, if *exclusive { "an exclusive" } else { "a shared" }, path),
+            StoreError::Format(message) => write!(f, "invalid table format: {}", message),
+            StoreError::MissingKey { space_id } => write!(f,
+                "space {} is encrypted, add its key to the store to read or write it", space_id),
+            StoreError::WrongKey { path } => write!(f,
+                "wrong key for {}, it was encrypted with another key", path),
+            StoreError::Truncated { path } => write!(f,
+                "encrypted table {} has been cut short of its last write", path),
+            StoreError::PatchConflicts(conflicts) => write!(f,
+                "patch not applied, {}", conflicts.iter().map(|x| x.to_string()).collect::<Vec<String>>().join(", ")),
+            StoreError::Io(e) => write!(f, "io error in triple store: {}", e),
+        }
+    }
+}
+
+impl Error for StoreError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StoreError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for StoreError {
+    fn from(e: io::Error) -> Self {
+        StoreError::Io(e)
+    }
+}
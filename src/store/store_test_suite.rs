@@ -0,0 +1,120 @@
+use super::triple_store::TripleStore;
+
+///
+/// The behavior every TripleStore implementation has to have. Each store's own tests call
+/// run_all() with a store working in a space of its own.
+///
+pub fn run_all<S: TripleStore>(store: &mut S) {
+    append_and_get(store);
+    append_many(store);
+    duplicates(store);
+    select(store);
+    get_triple(store);
+    clear(store);
+    replace(store);
+    spaces(store);
+    list_info_tables(store);
+}
+
+fn append_and_get<S: TripleStore>(store: &mut S) {
+    let table = "suite_append_table";
+    store.clear_infotable(table.to_string());
+    assert_eq!(store.get_info_table(table), "");
+
+    store.append_info_table(table, "id-a id1-a id2-a");
+    store.append_info_table(table, "id-b id1-b id2-b");
+    assert_eq!(store.get_info_table(table), "id-a id1-a id2-a\nid-b id1-b id2-b\n");
+}
+
+fn append_many<S: TripleStore>(store: &mut S) {
+    let table = "suite_append_many_table";
+    store.clear_infotable(table.to_string());
+    store.append_info_table(table, "id-a id1-a id2-a");
+    store.append_many(table, vec!["id-b id1-b id2-b".to_string(), "id-c id1-c id2-c".to_string()]);
+    store.append_many(table, Vec::new());
+    assert_eq!(store.get_info_table(table), "id-a id1-a id2-a\nid-b id1-b id2-b\nid-c id1-c id2-c\n");
+}
+
+fn duplicates<S: TripleStore>(store: &mut S) {
+    let table = "suite_duplicates_table";
+    store.clear_infotable(table.to_string());
+    store.append_info_table(table, "id-a id-x id2-a");
+    store.append_many(table, vec!["id-b id1-b id2-b".to_string(), "id-a id-x id2-a".to_string()]);
+    assert_eq!(store.get_info_table(table), "id-a id-x id2-a\nid-b id1-b id2-b\nid-a id-x id2-a\n");
+    assert_eq!(store.select_from_info_table(table, "id-x"), "id-a id-x id2-a\nid-a id-x id2-a\n");
+    assert_eq!(store.get_triple(table, "id-a"), Some("id-a id-x id2-a".to_string()));
+}
+
+fn select<S: TripleStore>(store: &mut S) {
+    let table = "suite_select_table";
+    store.clear_infotable(table.to_string());
+    store.append_many(table, vec![
+        "id-a id-x id2-a".to_string(),
+        "id-b id1-b id-x".to_string(),
+        "id-x id1-c id2-c".to_string(),
+        "id-d id1-d id2-d".to_string(),
+        "id-e id-xy id2-e".to_string(),
+    ]);
+    assert_eq!(store.select_from_info_table(table, "id-x"), "id-a id-x id2-a\nid-b id1-b id-x\n");
+    assert_eq!(store.select_from_info_table(table, "id-y"), "");
+    //ids are compared as a whole, not as substrings
+    assert_eq!(store.select_from_info_table(table, "id-"), "");
+}
+
+fn get_triple<S: TripleStore>(store: &mut S) {
+    let table = "suite_get_triple_table";
+    store.clear_infotable(table.to_string());
+    store.append_many(table, vec!["id-a id1-a id2-a".to_string(), "id-b id-a id2-b".to_string()]);
+    assert_eq!(store.get_triple(table, "id-b"), Some("id-b id-a id2-b".to_string()));
+    assert_eq!(store.get_triple(table, "id1-a"), None);
+    assert!(store.contains_triple(table, "id-a"));
+    assert!(!store.contains_triple(table, "id2-b"));
+}
+
+fn clear<S: TripleStore>(store: &mut S) {
+    let table = "suite_clear_table";
+    store.append_info_table(table, "id-a id1-a id2-a");
+    store.clear_infotable(table.to_string());
+    assert_eq!(store.get_info_table(table), "");
+    assert!(!store.contains_triple(table, "id-a"));
+}
+
+fn replace<S: TripleStore>(store: &mut S) {
+    let table = "suite_replace_table";
+    store.clear_infotable(table.to_string());
+    store.append_many(table, vec!["id-a id-x id2-a".to_string(), "id-b id1-b id2-b".to_string()]);
+    store.replace_info_table(table, vec!["id-c id1-c id2-c".to_string(), "id-b id1-b id2-b".to_string()]);
+    assert_eq!(store.get_info_table(table), "id-c id1-c id2-c\nid-b id1-b id2-b\n");
+    assert_eq!(store.select_from_info_table(table, "id-x"), "");
+    assert!(!store.contains_triple(table, "id-a"));
+    store.replace_info_table(table, Vec::new());
+    assert_eq!(store.get_info_table(table), "");
+}
+
+fn spaces<S: TripleStore>(store: &mut S) {
+    let table = "suite_space_table";
+    let org_space = store.get_space_id();
+    let guest_space = format!("{}_guest", org_space);
+    store.clear_infotable(table.to_string());
+    store.append_info_table(table, "id-a id1-a id2-a");
+
+    store.set_tmp_space_id(guest_space.clone());
+    assert_eq!(store.get_space_id(), guest_space);
+    store.clear_infotable(table.to_string());
+    assert_eq!(store.get_info_table(table), "");
+    store.append_info_table(table, "id-g id1-g id2-g");
+    assert_eq!(store.get_info_table(table), "id-g id1-g id2-g\n");
+    store.revert_space_id();
+
+    assert_eq!(store.get_space_id(), org_space);
+    assert_eq!(store.get_info_table(table), "id-a id1-a id2-a\n");
+}
+
+fn list_info_tables<S: TripleStore>(store: &mut S) {
+    let tables = store.list_info_tables();
+    assert!(tables.contains(&"suite_append_table".to_string()));
+    assert!(tables.contains(&"suite_select_table".to_string()));
+    //cleared tables stay listed, as in TripleStoreFS where the emptied file is kept
+    assert!(tables.contains(&"suite_clear_table".to_string()));
+    assert!(tables.windows(2).all(|x| x[0] < x[1]));
+}
@@ -0,0 +1,317 @@
+use std::fs;
+use std::io::{self, BufReader, Read};
+
+use super::store_error::StoreError;
+use super::table_file::{self, SyncPolicy};
+use super::table_format;
+
+///
+/// Whether the table files of a space are compressed. A compressed table file starts with a
+/// header (COMPRESSED_MAGIC and COMPRESSED_VERSION) followed by segments, each a 4 byte little
+/// endian length and a zstd frame. Every append adds a segment, so appending never rewrites the
+/// file, and the segments decompressed one after the other give the uncompressed table (text or
+/// binary).
+///
+/// The price of never rewriting is that a segment only compresses its own append: a table grown by
+/// single triples holds a small frame per triple, which can be larger than the text. Appending in
+/// batches (append_many()) keeps frames larger, and TripleStoreFS::compress_space() with the
+/// space's current compression recompacts every table to a single segment.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// zstd with the given compression level.
+    Zstd(i32),
+}
+
+pub const COMPRESSED_MAGIC: &[u8; 4] = b"IGTZ";
+pub const COMPRESSED_VERSION: u8 = 1;
+pub const DEFAULT_ZSTD_LEVEL: i32 = 3;
+/// The magic, the version and three bytes reserved for later versions.
+pub const HEADER_LEN: usize = 8;
+/// The length prefix of every segment (frame).
+pub const FRAME_LEN_LEN: usize = 4;
+
+impl Compression {
+    ///
+    /// The name used in the per space compression file, e.g. "zstd 3".
+    ///
+    pub fn name(&self) -> String {
+        match self {
+            Compression::None => "none".to_string(),
+            Compression::Zstd(level) => format!("zstd {}", level),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Compression> {
+        match name.split_whitespace().collect::<Vec<&str>>()[..] {
+            ["none"] => Some(Compression::None),
+            ["zstd"] => Some(Compression::Zstd(DEFAULT_ZSTD_LEVEL)),
+            ["zstd", level] => level.parse().ok().map(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+pub fn is_compressed(content: &[u8]) -> bool {
+    content.starts_with(COMPRESSED_MAGIC)
+}
+
+pub fn is_compressed_file(path: &str) -> io::Result<bool> {
+    let mut magic = [0u8; COMPRESSED_MAGIC.len()];
+    match fs::File::open(path) {
+        Ok(mut file) => match file.read_exact(&mut magic) {
+            Ok(()) => Ok(is_compressed(&magic)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+pub fn compressed_header() -> Vec<u8> {
+    let mut header = COMPRESSED_MAGIC.to_vec();
+    header.push(COMPRESSED_VERSION);
+    header.resize(HEADER_LEN, 0);
+    header
+}
+
+///
+/// Compresses uncompressed table content to one segment, to be appended to a compressed file.
+///
+pub fn compress_segment(payload: &[u8], level: i32) -> Result<Vec<u8>, StoreError> {
+    Ok(frame(zstd_compress(payload, level)?))
+}
+
+///
+/// Compresses uncompressed table content to the content of a compressed table file.
+///
+pub fn compress(payload: &[u8], level: i32) -> Result<Vec<u8>, StoreError> {
+    let mut content = compressed_header();
+    if !payload.is_empty() {
+        content.extend(compress_segment(payload, level)?);
+    }
+    Ok(content)
+}
+
+///
+/// Decompresses the content of a compressed table file. A partial segment at the end is left to
+/// recover_partial_segment().
+///
+pub fn decompress(content: &[u8]) -> Result<Vec<u8>, StoreError> {
+    let mut payload: Vec<u8> = Vec::new();
+    for_each_segment(&mut &content[..], |x| {
+        payload.extend(x);
+        true
+    })?;
+    Ok(payload)
+}
+
+///
+/// Reads a compressed table segment by segment, calling f with each decompressed segment until it
+/// returns false, so a table can be searched without decompressing all of it.
+///
+pub fn for_each_segment<R: Read, F: FnMut(Vec<u8>) -> bool>(reader: &mut R, mut f: F) -> Result<(), StoreError> {
+    for_each_frame(reader, COMPRESSED_MAGIC, COMPRESSED_VERSION, |frame| Ok(f(zstd_decompress(&frame)?)))?;
+    Ok(())
+}
+
+///
+/// Reads a framed file (a header with magic and version, then frames each prefixed with their 4
+/// byte little endian length), calling f with each frame until it returns false. Compressed and
+/// encrypted tables share this framing, and with it has_partial_segment() and
+/// recover_partial_segment().
+///
+/// Returns false if the file ends with a partial frame, which is not passed to f. Compressed
+/// tables skip it (it is recovered before the next write), encrypted tables refuse it.
+///
+pub fn for_each_frame<R, F>(reader: &mut R, magic: &[u8; 4], version: u8, mut f: F) -> Result<bool, StoreError>
+where
+    R: Read,
+    F: FnMut(Vec<u8>) -> Result<bool, StoreError>,
+{
+    let mut header = [0u8; HEADER_LEN];
+    match reader.read_exact(&mut header) {
+        Ok(()) => (),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(true),
+        Err(e) => return Err(e.into()),
+    }
+    if !header.starts_with(magic) {
+        return Err(StoreError::Format("missing table header".to_string()));
+    }
+    if header[magic.len()] != version {
+        return Err(StoreError::Format(format!("unsupported table version {}", header[magic.len()])));
+    }
+
+    loop {
+        //read_exact() cannot tell a clean end from a cut off length, so read the length bytewise
+        let mut len = [0u8; FRAME_LEN_LEN];
+        let mut read = 0;
+        while read < FRAME_LEN_LEN {
+            match reader.read(&mut len[read..]) {
+                Ok(0) => return Ok(read == 0),
+                Ok(n) => read += n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e.into()),
+            }
+        }
+        //the length is not trusted before the bytes are there, so the frame only grows with what
+        //is read rather than being allocated up front (up to 4 GiB for a corrupt length)
+        let len = u32::from_le_bytes(len) as u64;
+        let mut frame: Vec<u8> = Vec::new();
+        reader.by_ref().take(len).read_to_end(&mut frame)?;
+        if (frame.len() as u64) < len {
+            return Ok(false);
+        }
+        if !f(frame)? {
+            return Ok(true);
+        }
+    }
+}
+
+///
+/// Prefixes a frame with its length, see for_each_frame().
+///
+pub fn frame(body: Vec<u8>) -> Vec<u8> {
+    let mut frame = (body.len() as u32).to_le_bytes().to_vec();
+    frame.extend(body);
+    frame
+}
+
+///
+/// Returns the first decompressed segment of a compressed file, which is enough to tell the
+/// format of the uncompressed table.
+///
+pub fn read_first_segment(path: &str) -> Result<Vec<u8>, StoreError> {
+    let mut first: Vec<u8> = Vec::new();
+    for_each_segment(&mut BufReader::new(fs::File::open(path)?), |x| {
+        first = x;
+        false
+    })?;
+    Ok(first)
+}
+
+///
+/// Decodes table file content, compressed or not, in either table format to text lines.
+///
+pub fn decode_table(content: Vec<u8>) -> Result<String, StoreError> {
+    if is_compressed(&content) {
+        table_format::decode(decompress(&content)?)
+    } else {
+        table_format::decode(content)
+    }
+}
+
+///
+/// Returns the length of the file up to its last complete segment.
+///
+fn complete_len(path: &str) -> io::Result<(usize, usize)> {
+    let content = fs::read(path)?;
+    let mut complete = HEADER_LEN.min(content.len());
+    while complete + FRAME_LEN_LEN <= content.len() {
+        let mut len = [0u8; FRAME_LEN_LEN];
+        len.copy_from_slice(&content[complete..complete + FRAME_LEN_LEN]);
+        let end = complete + FRAME_LEN_LEN + u32::from_le_bytes(len) as usize;
+        if end > content.len() {
+            break;
+        }
+        complete = end;
+    }
+    Ok((complete, content.len()))
+}
+
+///
+/// The compressed version of table_file::has_partial_line(), true if the file ends with an
+/// incomplete segment.
+///
+pub fn has_partial_segment(path: &str) -> io::Result<bool> {
+    match complete_len(path) {
+        Ok((complete, len)) => Ok(complete != len),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+///
+/// The compressed version of table_file::recover_partial_line(), truncating the file to its last
+/// complete segment and keeping the partial segment hex encoded in path + ".partial".
+///
+pub fn recover_partial_segment(path: &str, policy: SyncPolicy) -> io::Result<Option<String>> {
+    let (complete, len) = match complete_len(path) {
+        Ok(x) => x,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if complete == len || complete < HEADER_LEN {
+        return Ok(None);
+    }
+
+    let content = fs::read(path)?;
+    let partial: String = content[complete..]
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+    table_file::append_lines(&format!("{}.partial", path), format!("{}\n", partial).as_bytes(), policy)?;
+    let file = fs::OpenOptions::new().write(true).open(path)?;
+    file.set_len(complete as u64)?;
+    if policy == SyncPolicy::Always {
+        file.sync_all()?;
+    }
+    Ok(Some(partial))
+}
+
+#[cfg(feature = "compression")]
+fn zstd_compress(payload: &[u8], level: i32) -> Result<Vec<u8>, StoreError> {
+    Ok(zstd::bulk::compress(payload, level)?)
+}
+
+#[cfg(feature = "compression")]
+fn zstd_decompress(frame: &[u8]) -> Result<Vec<u8>, StoreError> {
+    zstd::decode_all(frame)
+        .map_err(|e| StoreError::Format(format!("corrupt compressed segment: {}", e)))
+}
+
+#[cfg(not(feature = "compression"))]
+fn zstd_compress(_payload: &[u8], _level: i32) -> Result<Vec<u8>, StoreError> {
+    Err(StoreError::Format("compressed tables need the compression feature".to_string()))
+}
+
+#[cfg(not(feature = "compression"))]
+fn zstd_decompress(_frame: &[u8]) -> Result<Vec<u8>, StoreError> {
+    Err(StoreError::Format("compressed tables need the compression feature".to_string()))
+}
+
+
+#[cfg(feature = "compression")]
+#[test]
+fn segments_test() {
+    let mut content = compress(b"id-a id1-a id2-a\n", 3).unwrap();
+    content.extend(compress_segment(b"id-b id1-b id2-b\n", 3).unwrap());
+
+    assert!(is_compressed(&content));
+    assert_eq!(decode_table(content.clone()).unwrap(), "id-a id1-a id2-a\nid-b id1-b id2-b\n");
+
+    let mut segments: Vec<Vec<u8>> = Vec::new();
+    for_each_segment(&mut &content[..], |x| {
+        segments.push(x);
+        false
+    }).unwrap();
+    assert_eq!(segments, vec![b"id-a id1-a id2-a\n".to_vec()]);
+
+    //a cut off segment is skipped
+    content.truncate(content.len() - 3);
+    assert_eq!(decode_table(content).unwrap(), "id-a id1-a id2-a\n");
+    assert_eq!(Compression::from_name("zstd 19\n"), Some(Compression::Zstd(19)));
+    assert_eq!(Compression::from_name(&Compression::None.name()), Some(Compression::None));
+}
+
+#[test]
+fn corrupt_frame_length_test() {
+    //a length far beyond the bytes there is a partial frame, not a 4 GiB allocation
+    let mut content = compressed_header();
+    content.extend(u32::MAX.to_le_bytes());
+    content.extend(b"abc");
+    assert!(!for_each_frame(&mut &content[..], COMPRESSED_MAGIC, COMPRESSED_VERSION, |_| Ok(true)).unwrap());
+}
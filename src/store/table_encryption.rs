@@ -0,0 +1,256 @@
+use std::fs;
+use std::io::{self, Read};
+
+use super::store_error::StoreError;
+use super::table_compression::{self, for_each_frame, frame, FRAME_LEN_LEN};
+
+///
+/// The key of an encrypted space, supplied by the caller and never written to disk.
+///
+pub type SpaceKey = [u8; 32];
+
+///
+/// An encrypted table file starts with a header (ENCRYPTED_MAGIC and ENCRYPTED_VERSION) followed
+/// by frames as in compressed tables (see table_compression::for_each_frame()), each holding a
+/// random nonce and a ChaCha20-Poly1305 ciphertext. The first frame is a key check, KEY_CHECK
+/// encrypted with the table's key, so a wrong key gives StoreError::WrongKey before anything is
+/// parsed. Every append adds a frame, and the frames decrypted one after the other give the
+/// table content below (compressed or not, text or binary).
+///
+/// Every other frame is authenticated together with its table's context (see
+/// TripleStoreFS) and its offset in the file, so frames cannot be moved to another table or
+/// place. Cutting frames off the end is caught with a length commitment kept next to the table
+/// (see commitment()): a file shorter than its commitment gives StoreError::Truncated, and so
+/// does a partial frame at the end. Rolling back a table together with its commitment, or removing
+/// both, is not detected.
+///
+pub const ENCRYPTED_MAGIC: &[u8; 4] = b"IGTE";
+pub const ENCRYPTED_VERSION: u8 = 2;
+pub const KEY_CHECK: &[u8] = b"ig_tr key check";
+#[cfg(feature = "encryption")]
+const NONCE_LEN: usize = 12;
+
+pub fn is_encrypted(content: &[u8]) -> bool {
+    content.starts_with(ENCRYPTED_MAGIC)
+}
+
+pub fn is_encrypted_file(path: &str) -> io::Result<bool> {
+    let mut magic = [0u8; ENCRYPTED_MAGIC.len()];
+    match fs::File::open(path) {
+        Ok(mut file) => match file.read_exact(&mut magic) {
+            Ok(()) => Ok(is_encrypted(&magic)),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(e) => Err(e),
+        },
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn frame_aad(context: &str, offset: u64) -> Vec<u8> {
+    format!("ig_tr frame {} {}", context, offset).into_bytes()
+}
+
+fn commitment_aad(context: &str) -> Vec<u8> {
+    format!("ig_tr length {}", context).into_bytes()
+}
+
+///
+/// Returns a key check frame for key, also used in the per space encryption file to check keys
+/// added for the space.
+///
+pub fn key_check(key: &SpaceKey) -> Result<Vec<u8>, StoreError> {
+    Ok(frame(encrypt_frame(KEY_CHECK, key, &[])?))
+}
+
+///
+/// Returns true if the key check frame was made with key.
+///
+pub fn verify_key(key_check: &[u8], key: &SpaceKey) -> bool {
+    key_check.len() > FRAME_LEN_LEN
+        && decrypt_frame(&key_check[FRAME_LEN_LEN..], key, &[]).is_ok_and(|x| x == KEY_CHECK)
+}
+
+///
+/// Encrypts uncompressed or compressed table content to one frame, to be appended to an
+/// encrypted file of len bytes.
+///
+pub fn encrypt_segment(payload: &[u8], key: &SpaceKey, context: &str, len: u64) -> Result<Vec<u8>, StoreError> {
+    Ok(frame(encrypt_frame(payload, key, &frame_aad(context, len))?))
+}
+
+///
+/// Encrypts table content to the content of an encrypted table file.
+///
+pub fn encrypt(payload: &[u8], key: &SpaceKey, context: &str) -> Result<Vec<u8>, StoreError> {
+    let mut content = ENCRYPTED_MAGIC.to_vec();
+    content.push(ENCRYPTED_VERSION);
+    content.resize(table_compression::HEADER_LEN, 0);
+    content.extend(key_check(key)?);
+    if !payload.is_empty() {
+        let segment = encrypt_segment(payload, key, context, content.len() as u64)?;
+        content.extend(segment);
+    }
+    Ok(content)
+}
+
+///
+/// Decrypts the content of an encrypted table file, read from path (used in errors).
+///
+pub fn decrypt(content: &[u8], key: &SpaceKey, context: &str, path: &str) -> Result<Vec<u8>, StoreError> {
+    let mut payload: Vec<u8> = Vec::new();
+    for_each_segment(&mut &content[..], key, context, path, |x| {
+        payload.extend(x);
+        true
+    })?;
+    Ok(payload)
+}
+
+///
+/// Returns the first decrypted segment after the key check, which is enough to tell the
+/// compression and format of the table below.
+///
+pub fn read_first_segment(path: &str, key: &SpaceKey, context: &str) -> Result<Vec<u8>, StoreError> {
+    let mut first: Vec<u8> = Vec::new();
+    for_each_segment(&mut io::BufReader::new(fs::File::open(path)?), key, context, path, |x| {
+        first = x;
+        false
+    })?;
+    Ok(first)
+}
+
+///
+/// Reads an encrypted table segment by segment, checking the key first, and calling f with each
+/// decrypted segment until it returns false.
+///
+pub fn for_each_segment<R, F>(reader: &mut R, key: &SpaceKey, context: &str, path: &str, mut f: F) -> Result<(), StoreError>
+where
+    R: Read,
+    F: FnMut(Vec<u8>) -> bool,
+{
+    if cfg!(not(feature = "encryption")) {
+        return Err(StoreError::Format("encrypted tables need the encryption feature".to_string()));
+    }
+    let mut checked = false;
+    let mut offset = table_compression::HEADER_LEN as u64;
+    let complete = for_each_frame(reader, ENCRYPTED_MAGIC, ENCRYPTED_VERSION, |x| {
+        let frame_offset = offset;
+        offset += (FRAME_LEN_LEN + x.len()) as u64;
+        if !checked {
+            checked = true;
+            return match decrypt_frame(&x, key, &[]) {
+                Ok(check) if check == KEY_CHECK => Ok(true),
+                _ => Err(StoreError::WrongKey { path: path.to_string() }),
+            };
+        }
+        //the key was checked, so a failing frame has been tampered with or moved
+        decrypt_frame(&x, key, &frame_aad(context, frame_offset))
+            .map(&mut f)
+            .map_err(|_| StoreError::Format(format!("encrypted segment in {} failed authentication", path)))
+    })?;
+    if !complete {
+        return Err(StoreError::Truncated { path: path.to_string() });
+    }
+    Ok(())
+}
+
+///
+/// Returns the length commitment of a table file of len bytes, authenticated with key and the
+/// table's context.
+///
+pub fn commitment(len: u64, key: &SpaceKey, context: &str) -> Result<Vec<u8>, StoreError> {
+    encrypt_frame(len.to_string().as_bytes(), key, &commitment_aad(context))
+}
+
+///
+/// Checks a table file of len bytes at path against its commitment, which is missing only for
+/// tables that were never written encrypted.
+///
+pub fn check_commitment(len: u64, commitment: Option<&[u8]>, key: &SpaceKey, context: &str, path: &str) -> Result<(), StoreError> {
+    let committed = match commitment {
+        Some(commitment) => decrypt_frame(commitment, key, &commitment_aad(context))
+            .ok()
+            .and_then(|x| String::from_utf8(x).ok())
+            .and_then(|x| x.parse::<u64>().ok())
+            .ok_or_else(|| StoreError::Format(format!("length commitment of {} failed authentication", path)))?,
+        None if len == 0 => 0,
+        None => return Err(StoreError::Format(format!("length commitment of {} is missing", path))),
+    };
+    if len < committed {
+        return Err(StoreError::Truncated { path: path.to_string() });
+    }
+    Ok(())
+}
+
+#[cfg(feature = "encryption")]
+fn encrypt_frame(payload: &[u8], key: &SpaceKey, aad: &[u8]) -> Result<Vec<u8>, StoreError> {
+    use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng, Payload};
+    use chacha20poly1305::ChaCha20Poly1305;
+
+    let cipher = ChaCha20Poly1305::new(key.into());
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: payload, aad })
+        .map_err(|_| StoreError::Format("encryption failed".to_string()))?;
+    let mut body = nonce.to_vec();
+    body.extend(ciphertext);
+    Ok(body)
+}
+
+#[cfg(feature = "encryption")]
+fn decrypt_frame(body: &[u8], key: &SpaceKey, aad: &[u8]) -> Result<Vec<u8>, StoreError> {
+    use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+    use chacha20poly1305::{ChaCha20Poly1305, Nonce};
+
+    if body.len() < NONCE_LEN {
+        return Err(StoreError::Format("encrypted segment too short".to_string()));
+    }
+    let (nonce, ciphertext) = body.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(key.into())
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+        .map_err(|_| StoreError::Format("decryption failed".to_string()))
+}
+
+#[cfg(not(feature = "encryption"))]
+fn encrypt_frame(_payload: &[u8], _key: &SpaceKey, _aad: &[u8]) -> Result<Vec<u8>, StoreError> {
+    Err(StoreError::Format("encrypted tables need the encryption feature".to_string()))
+}
+
+#[cfg(not(feature = "encryption"))]
+fn decrypt_frame(_body: &[u8], _key: &SpaceKey, _aad: &[u8]) -> Result<Vec<u8>, StoreError> {
+    Err(StoreError::Format("encrypted tables need the encryption feature".to_string()))
+}
+
+
+#[cfg(feature = "encryption")]
+#[test]
+fn encrypt_decrypt_test() {
+    let key: SpaceKey = [7u8; 32];
+    let context = "space/main_table";
+    let mut content = encrypt(b"id-a id1-a id2-a\n", &key, context).unwrap();
+    let first_len = content.len();
+    let segment = encrypt_segment(b"id-b id1-b id2-b\n", &key, context, content.len() as u64).unwrap();
+    content.extend(segment);
+
+    assert!(is_encrypted(&content));
+    assert_eq!(decrypt(&content, &key, context, "main_table").unwrap(), b"id-a id1-a id2-a\nid-b id1-b id2-b\n".to_vec());
+    assert!(matches!(decrypt(&content, &[8u8; 32], context, "main_table"), Err(StoreError::WrongKey{ .. })));
+    assert!(verify_key(&key_check(&key).unwrap(), &key));
+    assert!(!verify_key(&key_check(&key).unwrap(), &[8u8; 32]));
+
+    //frames are bound to their table
+    assert!(matches!(decrypt(&content, &key, "space/other_table", "main_table"), Err(StoreError::Format(_))));
+
+    //a partial frame and a file cut short of its commitment are refused
+    let commitment = commitment(content.len() as u64, &key, context).unwrap();
+    assert!(check_commitment(content.len() as u64, Some(&commitment), &key, context, "main_table").is_ok());
+    assert!(matches!(decrypt(&content[..content.len() - 3], &key, context, "main_table"), Err(StoreError::Truncated{ .. })));
+    assert!(decrypt(&content[..first_len], &key, context, "main_table").is_ok());
+    assert!(matches!(
+        check_commitment(first_len as u64, Some(&commitment), &key, context, "main_table"),
+        Err(StoreError::Truncated{ .. })
+    ));
+    assert!(check_commitment(first_len as u64, Some(&commitment), &key, "space/other_table", "main_table").is_err());
+    assert!(check_commitment(first_len as u64, None, &key, context, "main_table").is_err());
+}
+
@@ -0,0 +1,302 @@
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use fs2::FileExt;
+
+use super::store_error::StoreError;
+
+///
+/// When TripleStoreFS asks the operating system to flush table files to disk. The default is
+/// Never, as writes were before there was a policy; stores that need writes to survive a crash
+/// opt in with TripleStoreFS::set_sync_policy(SyncPolicy::Always).
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SyncPolicy {
+    /// fsync after every write, so a write that returned survives a crash.
+    Always,
+    /// Leave flushing to the operating system. Faster, but the latest writes can be lost on a
+    /// crash (never half lines though, see recover_partial_line()).
+    #[default]
+    Never,
+}
+
+///
+/// Appends lines (each ending with '\n', or binary records) to the file with a single write, so
+/// another append cannot end up in the middle of them. A crash during the write can leave the
+/// first lines of the batch followed by a partial line, which recover_partial_line() cuts off.
+///
+pub fn append_lines(path: &str, lines: &[u8], policy: SyncPolicy) -> io::Result<()> {
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)?;
+    file.write_all(lines)?;
+    if policy == SyncPolicy::Always {
+        file.sync_all()?;
+    }
+    Ok(())
+}
+
+///
+/// Replaces the content of the file by writing a temp file and renaming it over the file, so a
+/// crash leaves either the old or the new content.
+///
+pub fn replace_file(path: &str, content: &[u8], policy: SyncPolicy) -> io::Result<()> {
+    let tmp_path = write_tmp_file(path, content, policy)?;
+    rename(&tmp_path, path, policy)
+}
+
+///
+/// Writes content to a temp file next to path and returns the temp file's path, for replacing
+/// several files with all new contents on disk before the first of them is renamed (see
+/// rename_over()).
+///
+pub fn write_tmp_file(path: &str, content: &[u8], policy: SyncPolicy) -> io::Result<String> {
+    let tmp_path = tmp_path(path);
+    let mut file = fs::File::create(&tmp_path)?;
+    file.write_all(content)?;
+    if policy == SyncPolicy::Always {
+        file.sync_all()?;
+    }
+    Ok(tmp_path)
+}
+
+///
+/// Renames a temp file written by write_tmp_file() over path.
+///
+pub fn rename_over(tmp_path: &str, path: &str, policy: SyncPolicy) -> io::Result<()> {
+    rename(tmp_path, path, policy)
+}
+
+///
+/// Returns true if the file does not end with a complete line, which is a cheap check only
+/// reading the last byte.
+///
+pub fn has_partial_line(path: &str) -> io::Result<bool> {
+    match fs::File::open(path) {
+        Ok(mut file) => ends_with_partial_line(&mut file),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+fn ends_with_partial_line(file: &mut fs::File) -> io::Result<bool> {
+    let len = file.seek(SeekFrom::End(0))?;
+    if len == 0 {
+        return Ok(false);
+    }
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] != b'\n')
+}
+
+///
+/// If the process died in the middle of a write the file can end with a partial line. This
+/// function truncates the file back to its last complete line and moves the partial line to a
+/// quarantine file next to it (path + ".partial"), returning the partial line if one was found.
+///
+pub fn recover_partial_line(path: &str, policy: SyncPolicy) -> io::Result<Option<String>> {
+    let mut file = match fs::OpenOptions::new().read(true).write(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if !ends_with_partial_line(&mut file)? {
+        return Ok(None);
+    }
+
+    let mut content: Vec<u8> = Vec::new();
+    file.seek(SeekFrom::Start(0))?;
+    file.read_to_end(&mut content)?;
+    let keep = content.iter().rposition(|b| *b == b'\n').map_or(0, |i| i + 1);
+    let partial = String::from_utf8_lossy(&content[keep..]).to_string();
+
+    append_lines(&format!("{}.partial", path), format!("{}\n", partial).as_bytes(), policy)?;
+    file.set_len(keep as u64)?;
+    if policy == SyncPolicy::Always {
+        file.sync_all()?;
+    }
+    Ok(Some(partial))
+}
+
+///
+/// An advisory lock on a table, held until it is dropped. The lock is taken on a separate lock
+/// file (path + ".lock") rather than on the table file itself, since writes that rename a new
+/// file over the table file would otherwise leave the lock on the old file.
+///
+pub struct TableLock {
+    file: fs::File,
+}
+
+impl Drop for TableLock {
+    fn drop(&mut self) {
+        let _ = FileExt::unlock(&self.file);
+    }
+}
+
+///
+/// Returns the last complete line of the file without its '\n', reading back from the end in
+/// small chunks rather than reading the whole file. A partial line at the end is left out.
+///
+pub fn read_last_line(path: &str) -> io::Result<Option<String>> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    let mut pos = file.seek(SeekFrom::End(0))?;
+    let mut tail: Vec<u8> = Vec::new();
+    loop {
+        if let Some(end) = tail.iter().rposition(|x| *x == b'\n') {
+            match tail[..end].iter().rposition(|x| *x == b'\n') {
+                Some(start) => return Ok(Some(String::from_utf8_lossy(&tail[start + 1..end]).to_string())),
+                None if pos == 0 => return Ok(Some(String::from_utf8_lossy(&tail[..end]).to_string())),
+                None => (),
+            }
+        } else if pos == 0 {
+            return Ok(None);
+        }
+        let read = pos.min(256);
+        pos -= read;
+        let mut chunk = vec![0u8; read as usize];
+        file.seek(SeekFrom::Start(pos))?;
+        file.read_exact(&mut chunk)?;
+        chunk.extend(tail);
+        tail = chunk;
+    }
+}
+
+///
+/// Takes a shared lock on the table, so several readers can hold it at the same time while
+/// writers wait. Gives up with StoreError::LockTimeout after timeout.
+///
+pub fn lock_shared(path: &str, timeout: Duration) -> Result<TableLock, StoreError> {
+    lock(path, false, timeout)
+}
+
+///
+/// Takes an exclusive lock on the table, for writes and clears. Gives up with
+/// StoreError::LockTimeout after timeout.
+///
+pub fn lock_exclusive(path: &str, timeout: Duration) -> Result<TableLock, StoreError> {
+    lock(path, true, timeout)
+}
+
+fn lock(path: &str, exclusive: bool, timeout: Duration) -> Result<TableLock, StoreError> {
+    let lock_path = format!("{}.lock", path);
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)?;
+    let started = Instant::now();
+    loop {
+        let result = if exclusive { FileExt::try_lock_exclusive(&file) } else { FileExt::try_lock_shared(&file) };
+        match result {
+            Ok(()) => return Ok(TableLock { file }),
+            Err(e) if e.kind() != fs2::lock_contended_error().kind() => return Err(e.into()),
+            Err(_) if started.elapsed() >= timeout => {
+                return Err(StoreError::LockTimeout { path: lock_path, exclusive, timeout });
+            },
+            Err(_) => thread::sleep(Duration::from_millis(10)),
+        }
+    }
+}
+
+fn tmp_path(path: &str) -> String {
+    format!("{}.{}.tmp", path, std::process::id())
+}
+
+fn rename(from: &str, to: &str, policy: SyncPolicy) -> io::Result<()> {
+    fs::rename(from, to)?;
+    //the rename itself is only durable once the directory is synced
+    #[cfg(unix)]
+    if policy == SyncPolicy::Always {
+        if let Some(dir) = Path::new(to).parent() {
+            fs::File::open(dir)?.sync_all()?;
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = policy;
+    Ok(())
+}
+
+
+#[test]
+fn recover_partial_line_test() {
+    let dir = std::env::temp_dir().join(format!("ig_tr_recover_{}", std::process::id()));
+    let _ = fs::create_dir_all(&dir);
+    let path = dir.join("main_table").to_string_lossy().to_string();
+    let _ = fs::remove_file(format!("{}.partial", path));
+
+    fs::write(&path, "id-a id1-a id2-a\nid-b id1").unwrap();
+    assert!(has_partial_line(&path).unwrap());
+    assert_eq!(recover_partial_line(&path, SyncPolicy::Always).unwrap(), Some("id-b id1".to_string()));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "id-a id1-a id2-a\n");
+    assert_eq!(fs::read_to_string(format!("{}.partial", path)).unwrap(), "id-b id1\n");
+
+    assert!(!has_partial_line(&path).unwrap());
+    assert_eq!(recover_partial_line(&path, SyncPolicy::Always).unwrap(), None);
+    assert_eq!(recover_partial_line(&format!("{}.missing", path), SyncPolicy::Always).unwrap(), None);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn lock_test() {
+    let dir = std::env::temp_dir().join(format!("ig_tr_lock_{}", std::process::id()));
+    let _ = fs::create_dir_all(&dir);
+    let path = dir.join("main_table").to_string_lossy().to_string();
+    let timeout = Duration::from_millis(50);
+
+    let shared1 = lock_shared(&path, timeout).unwrap();
+    let shared2 = lock_shared(&path, timeout).unwrap();
+    assert!(matches!(lock_exclusive(&path, timeout), Err(StoreError::LockTimeout{ exclusive: true, .. })));
+    drop(shared1);
+    drop(shared2);
+
+    let exclusive = lock_exclusive(&path, timeout).unwrap();
+    assert!(matches!(lock_shared(&path, timeout), Err(StoreError::LockTimeout{ exclusive: false, .. })));
+    drop(exclusive);
+    assert!(lock_shared(&path, timeout).is_ok());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn replace_file_test() {
+    let dir = std::env::temp_dir().join(format!("ig_tr_replace_{}", std::process::id()));
+    let _ = fs::create_dir_all(&dir);
+    let path = dir.join("main_table").to_string_lossy().to_string();
+
+    fs::write(&path, "id-a id1-a id2-a\n").unwrap();
+    replace_file(&path, b"", SyncPolicy::Always).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "");
+    append_lines(&path, b"id-b id1-b id2-b\n", SyncPolicy::Never).unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "id-b id1-b id2-b\n");
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn read_last_line_test() {
+    let dir = std::env::temp_dir().join(format!("ig_tr_last_line_{}", std::process::id()));
+    let _ = fs::create_dir_all(&dir);
+    let path = dir.join("main_table.ts").to_string_lossy().to_string();
+
+    assert_eq!(read_last_line(&path).unwrap(), None);
+    fs::write(&path, "").unwrap();
+    assert_eq!(read_last_line(&path).unwrap(), None);
+    fs::write(&path, "1 1 17\n").unwrap();
+    assert_eq!(read_last_line(&path).unwrap(), Some("1 1 17".to_string()));
+    let long: String = (0..100).map(|i| format!("{} {} {}\n", i, i, i * 17)).collect();
+    fs::write(&path, format!("{}100 100", long)).unwrap();
+    assert_eq!(read_last_line(&path).unwrap(), Some("99 99 1683".to_string()));
+
+    let _ = fs::remove_dir_all(&dir);
+}
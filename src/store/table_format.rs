@@ -0,0 +1,284 @@
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use super::store_error::StoreError;
+use super::table_file::{self, SyncPolicy};
+
+///
+/// How the triples of a table file are stored. Text tables have a "id id1 id2" line per triple,
+/// binary tables start with a header (BINARY_MAGIC and BINARY_VERSION) followed by a
+/// RECORD_LEN byte record per triple, holding the three 32 byte hashes.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TableFormat {
+    #[default]
+    Text,
+    Binary,
+}
+
+pub const BINARY_MAGIC: &[u8; 4] = b"IGTB";
+pub const BINARY_VERSION: u8 = 1;
+/// The magic, the version and three bytes reserved for later versions.
+pub const HEADER_LEN: usize = 8;
+pub const HASH_LEN: usize = 32;
+pub const RECORD_LEN: usize = 3 * HASH_LEN;
+
+impl TableFormat {
+    ///
+    /// The format of a table file's content, where empty content counts as text.
+    ///
+    pub fn detect(content: &[u8]) -> TableFormat {
+        if content.starts_with(BINARY_MAGIC) {
+            TableFormat::Binary
+        } else {
+            TableFormat::Text
+        }
+    }
+
+    pub fn detect_file(path: &str) -> io::Result<TableFormat> {
+        let mut magic = [0u8; BINARY_MAGIC.len()];
+        match fs::File::open(path) {
+            Ok(mut file) => match file.read_exact(&mut magic) {
+                Ok(()) => Ok(TableFormat::detect(&magic)),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(TableFormat::Text),
+                Err(e) => Err(e),
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(TableFormat::Text),
+            Err(e) => Err(e),
+        }
+    }
+
+    ///
+    /// The name used in the per space format file.
+    ///
+    pub fn name(&self) -> &'static str {
+        match self {
+            TableFormat::Text => "text",
+            TableFormat::Binary => "binary",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TableFormat> {
+        match name.trim() {
+            "text" => Some(TableFormat::Text),
+            "binary" => Some(TableFormat::Binary),
+            _ => None,
+        }
+    }
+}
+
+pub fn binary_header() -> Vec<u8> {
+    let mut header = BINARY_MAGIC.to_vec();
+    header.push(BINARY_VERSION);
+    header.resize(HEADER_LEN, 0);
+    header
+}
+
+///
+/// Encodes text lines as binary records (without header). Every id has to be a 64 character hex
+/// hash, as produced by the crate's hashing.
+///
+pub fn encode_records(lines: &str) -> Result<Vec<u8>, StoreError> {
+    let mut records: Vec<u8> = Vec::new();
+    for line in lines.lines() {
+        let ids: Vec<&str> = line.split(' ').collect();
+        if ids.len() != 3 {
+            return Err(StoreError::Format(format!("\"{}\" is not a triple of three ids", line)));
+        }
+        for id in ids {
+            decode_hex(id, &mut records)?;
+        }
+    }
+    Ok(records)
+}
+
+///
+/// Decodes binary table content (with header) to text lines. Empty content decodes to an empty
+/// table.
+///
+pub fn decode_binary(content: &[u8]) -> Result<String, StoreError> {
+    if content.is_empty() {
+        return Ok(String::new());
+    }
+    if content.len() < HEADER_LEN {
+        return Err(StoreError::Format("missing binary table header".to_string()));
+    }
+    check_header(&content[..HEADER_LEN])?;
+
+    let mut lines = String::with_capacity((content.len() - HEADER_LEN) / RECORD_LEN * (3 * 2 * HASH_LEN + 3));
+    //a partial record at the end is left to recover_partial_record()
+    content[HEADER_LEN..]
+        .chunks_exact(RECORD_LEN)
+        .for_each(|record|{
+            encode_record(record, &mut lines);
+            lines.push('\n');
+        });
+    Ok(lines)
+}
+
+///
+/// Returns the line of the first record with the id triple_id in binary table content (with
+/// header), reading and comparing one record at a time instead of decoding the whole table.
+///
+pub fn find_record<R: Read>(reader: &mut R, triple_id: &str) -> Result<Option<String>, StoreError> {
+    let mut id: Vec<u8> = Vec::with_capacity(HASH_LEN);
+    if decode_hex(triple_id, &mut id).is_err() {
+        //a binary table only holds hex hashes
+        return Ok(None);
+    }
+    let mut header = [0u8; HEADER_LEN];
+    match reader.read_exact(&mut header) {
+        Ok(()) => check_header(&header)?,
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut record = [0u8; RECORD_LEN];
+    loop {
+        match reader.read_exact(&mut record) {
+            Ok(()) if record[..HASH_LEN] == id[..] => {
+                let mut line = String::with_capacity(3 * 2 * HASH_LEN + 2);
+                encode_record(&record, &mut line);
+                return Ok(Some(line));
+            },
+            Ok(()) => (),
+            //a partial record at the end is left to recover_partial_record()
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        }
+    }
+}
+
+fn check_header(header: &[u8]) -> Result<(), StoreError> {
+    if !header.starts_with(BINARY_MAGIC) {
+        return Err(StoreError::Format("missing binary table header".to_string()));
+    }
+    if header[BINARY_MAGIC.len()] != BINARY_VERSION {
+        return Err(StoreError::Format(format!("unsupported binary table version {}", header[BINARY_MAGIC.len()])));
+    }
+    Ok(())
+}
+
+fn encode_record(record: &[u8], out: &mut String) {
+    record
+        .chunks_exact(HASH_LEN)
+        .enumerate()
+        .for_each(|(i, hash)|{
+            if i > 0 {
+                out.push(' ');
+            }
+            encode_hex(hash, out);
+        });
+}
+
+///
+/// Decodes table content in either format to text lines.
+///
+pub fn decode(content: Vec<u8>) -> Result<String, StoreError> {
+    match TableFormat::detect(&content) {
+        TableFormat::Binary => decode_binary(&content),
+        TableFormat::Text => String::from_utf8(content)
+            .map_err(|e| StoreError::Format(format!("text table is not valid utf-8: {}", e))),
+    }
+}
+
+///
+/// Converts text table content to binary table content.
+///
+pub fn text_to_binary(text: &str) -> Result<Vec<u8>, StoreError> {
+    let mut content = binary_header();
+    content.extend(encode_records(text)?);
+    Ok(content)
+}
+
+///
+/// Converts binary table content to text table content.
+///
+pub fn binary_to_text(content: &[u8]) -> Result<String, StoreError> {
+    decode_binary(content)
+}
+
+///
+/// The binary version of table_file::has_partial_line(), true if the file ends with an
+/// incomplete record.
+///
+pub fn has_partial_record(path: &str) -> io::Result<bool> {
+    let len = match fs::metadata(path) {
+        Ok(metadata) => metadata.len() as usize,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(e),
+    };
+    Ok(len != 0 && (len < HEADER_LEN || !(len - HEADER_LEN).is_multiple_of(RECORD_LEN)))
+}
+
+///
+/// The binary version of table_file::recover_partial_line(), truncating the file to its last
+/// complete record and keeping the partial record hex encoded in path + ".partial".
+///
+pub fn recover_partial_record(path: &str, policy: SyncPolicy) -> io::Result<Option<String>> {
+    if !has_partial_record(path)? {
+        return Ok(None);
+    }
+    let mut file = fs::OpenOptions::new().read(true).write(true).open(path)?;
+    let len = file.seek(SeekFrom::End(0))? as usize;
+    let keep = if len < HEADER_LEN { len } else { len - (len - HEADER_LEN) % RECORD_LEN };
+    if keep == len {
+        //a file cut off inside its header cannot be recovered
+        return Ok(None);
+    }
+
+    let mut tail: Vec<u8> = Vec::new();
+    file.seek(SeekFrom::Start(keep as u64))?;
+    file.read_to_end(&mut tail)?;
+    let mut partial = String::new();
+    encode_hex(&tail, &mut partial);
+
+    table_file::append_lines(&format!("{}.partial", path), format!("{}\n", partial).as_bytes(), policy)?;
+    file.set_len(keep as u64)?;
+    if policy == SyncPolicy::Always {
+        file.sync_all()?;
+    }
+    Ok(Some(partial))
+}
+
+fn decode_hex(id: &str, out: &mut Vec<u8>) -> Result<(), StoreError> {
+    let invalid = || StoreError::Format(format!("\"{}\" is not a {} character hex hash", id, 2 * HASH_LEN));
+    if id.len() != 2 * HASH_LEN {
+        return Err(invalid());
+    }
+    for pair in id.as_bytes().chunks_exact(2) {
+        let high = (pair[0] as char).to_digit(16).ok_or_else(invalid)?;
+        let low = (pair[1] as char).to_digit(16).ok_or_else(invalid)?;
+        out.push((high * 16 + low) as u8);
+    }
+    Ok(())
+}
+
+fn encode_hex(bytes: &[u8], out: &mut String) {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    bytes
+        .iter()
+        .for_each(|b|{
+            out.push(DIGITS[(b >> 4) as usize] as char);
+            out.push(DIGITS[(b & 0xf) as usize] as char);
+        });
+}
+
+
+#[test]
+fn binary_roundtrip_test() {
+    let text = "cfe94de90878b723efdb311090465f2158798c3e9c21e82ac1582190290f756e 38a24bbddca56373b49d0452f5e485c11048c729d9230beb2fa41044a36e9791 5fe9374c5e9e27ebb78f8bf7cd78bbb23ee51e672dc54c603ec1c5b3eef33feb\n";
+    let binary = text_to_binary(text).unwrap();
+
+    assert_eq!(binary.len(), HEADER_LEN + RECORD_LEN);
+    assert_eq!(TableFormat::detect(&binary), TableFormat::Binary);
+    assert_eq!(binary_to_text(&binary).unwrap(), text);
+    assert_eq!(decode(binary.clone()).unwrap(), text);
+    assert_eq!(decode(text.as_bytes().to_vec()).unwrap(), text);
+    assert!(matches!(text_to_binary("id-a id1-a id2-a\n"), Err(StoreError::Format(_))));
+    assert!(matches!(decode_binary(b"IGTB\x09\0\0\0"), Err(StoreError::Format(_))));
+
+    assert_eq!(find_record(&mut &binary[..], &text[..64]).unwrap(), Some(text.trim_end().to_string()));
+    assert_eq!(find_record(&mut &binary[..], &text[65..129]).unwrap(), None);
+    assert_eq!(find_record(&mut &binary[..binary.len() - 1], &text[..64]).unwrap(), None);
+    assert_eq!(find_record(&mut &binary[..], "id-a").unwrap(), None);
+}
@@ -1,6 +1,6 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-use crate::{InfoTriple, InfoTable};
+use crate::{InfoTriple, TriplePosition, InfoTable, ConstructionTree, DagReport, SyncReport, TableSync, MerkleTree, MerkleProof, SpacePatch, TablePatch, TableVersion};
 
 use super::triple_store::TripleStore;
 
@@ -24,6 +24,13 @@ impl<T:TripleStore> TripleFacade<T> {
         self.storage.append_info_table(infotable_name, infotriple);
     }
 
+    pub fn add_many_to_infotable(&mut self, infotable_name: String, infotriples: Vec<InfoTriple>) {
+        self.storage.append_many(
+            &infotable_name,
+            infotriples.iter().map(|x| x.to_tuple_string()),
+        );
+    }
+
     pub fn get_ids_from_info_table(&mut self, infotable_name: &str) -> Vec<String> {
 
             self.storage.get_info_table(infotable_name)
@@ -52,6 +59,46 @@ impl<T:TripleStore> TripleFacade<T> {
             .collect()
     }
     
+    ///
+    /// Returns the current version of the table, its generation and number of lines, to read it
+    /// as of later.
+    ///
+    pub fn get_info_table_version(&mut self, infotable_name: &str) -> TableVersion {
+        //the generation is taken first, so a rewrite in between gives a version that no longer
+        //applies rather than one of the rewritten table
+        let generation = self.storage.table_generation(infotable_name).unwrap_or(0);
+        let lines = self.storage.get_info_table(infotable_name).lines().count() as u64;
+        TableVersion::Seq { generation, lines }
+    }
+
+    ///
+    /// Returns the triples the table had at version. As tables are append-only logs the table as
+    /// of a version with n lines is its first n lines. Gives None if the table was rewritten
+    /// since (its generation changed) or has fewer lines than the version, and for
+    /// TableVersion::Time if the store cannot tell how many lines the table had at that time
+    /// (see TripleStore::version_at()).
+    ///
+    pub fn get_info_table_as_of(&mut self, infotable_name: &str, version: TableVersion) -> Option<Vec<InfoTriple>> {
+        let (generation, seq) = match version {
+            TableVersion::Seq { generation, lines } => (Some(generation), lines),
+            TableVersion::Time(time) => (None, self.storage.version_at(infotable_name, time)?),
+        };
+        let content = self.storage.get_info_table(infotable_name);
+        //checked after reading, so a rewrite while reading is noticed
+        if generation.is_some_and(|x| x != self.storage.table_generation(infotable_name).unwrap_or(0)) {
+            return None;
+        }
+        let lines: Vec<&str> = content.lines().collect();
+        if seq > lines.len() as u64 {
+            return None;
+        }
+        Some(lines
+            .into_iter()
+            .take(seq as usize)
+            .map(|x|InfoTriple::from(x.to_string()))
+            .collect())
+    }
+
     pub fn get_info_table_as_info_table(&mut self, infotable_name: &str) -> InfoTable {
         self.storage.get_info_table(infotable_name)
             .lines()
@@ -66,6 +113,15 @@ impl<T:TripleStore> TripleFacade<T> {
             .collect()
     }
 
+    pub fn get_triple(&mut self, infotable_name: &str, triple_id: &str) -> Option<InfoTriple> {
+        self.storage.get_triple(infotable_name, triple_id)
+            .map(InfoTriple::from)
+    }
+
+    pub fn contains_triple(&mut self, infotable_name: &str, triple_id: &str) -> bool {
+        self.storage.contains_triple(infotable_name, triple_id)
+    }
+
     pub fn get_all_ids_from_info_table_select(&mut self, infotable_name: &str, where_id: &str) -> HashSet<String> {
 
         self.storage.select_from_info_table(infotable_name, where_id)
@@ -80,8 +136,195 @@ impl<T:TripleStore> TripleFacade<T> {
                     .collect()
     }
 
+    ///
+    /// Like select_from_info_table() but only the triples where where_id is ID1.
+    ///
+    pub fn select_outgoing_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> Vec<InfoTriple> {
+        self.select_from_info_table(infotable_name, where_id)
+            .into_iter()
+            .filter(|t| t.id1 == where_id)
+            .collect()
+    }
+
+    ///
+    /// Like select_from_info_table() but only the triples where where_id is ID2.
+    ///
+    pub fn select_incoming_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> Vec<InfoTriple> {
+        self.select_from_info_table(infotable_name, where_id)
+            .into_iter()
+            .filter(|t| t.id2 == where_id)
+            .collect()
+    }
+
+    ///
+    /// Returns the position where_id has in each triple pairing it and the neighbor id, keyed by
+    /// triple id. See InfoTable::get_neighbor_positions().
+    ///
+    pub fn get_neighbor_positions_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> HashMap<String, (TriplePosition, String)> {
+        self.select_from_info_table(infotable_name, where_id)
+            .into_iter()
+            .filter_map(|t|{
+                let position = t.position_of(where_id)?;
+                let neighbor = if position == TriplePosition::Id2 { t.id1 } else { t.id2 };
+                Some((t.id, (position, neighbor)))
+            })
+            .collect()
+    }
+
     pub fn clear_infotable(&mut self, infotable_name: String) {
         self.storage.clear_infotable(infotable_name);
     }
-}
 
+    pub fn decompose_from_info_table(&mut self, infotable_name: &str, id: &str, max_depth: Option<usize>) -> ConstructionTree {
+        self.get_info_table_as_info_table(infotable_name)
+            .decompose(id.to_string(), max_depth)
+    }
+
+    ///
+    /// Like InfoTable::get_ancestors() but asks the storage level by level with
+    /// select_from_info_table() instead of loading the whole table.
+    ///
+    pub fn get_ancestors_from_info_table(&mut self, infotable_name: &str, id: &str) -> HashMap<String, usize> {
+        let mut ancestors: HashMap<String, usize> = HashMap::new();
+        let mut level_ids: Vec<String> = vec![id.to_string()];
+        let mut level: usize = 0;
+
+        while !level_ids.is_empty() {
+            level += 1;
+            let mut next_level_ids: Vec<String> = Vec::new();
+            for id_x in level_ids {
+                self.select_from_info_table(infotable_name, &id_x)
+                    .into_iter()
+                    .filter(|t| t.id != id)
+                    .for_each(|t|{
+                        if !ancestors.contains_key(&t.id) {
+                            ancestors.insert(t.id.clone(), level);
+                            next_level_ids.push(t.id);
+                        }
+                    });
+            }
+            level_ids = next_level_ids;
+        }
+        ancestors
+    }
+
+    ///
+    /// Validates that an info table is a DAG, see InfoTable::validate_dag(). Useful after
+    /// appending imported data with append_info_table().
+    ///
+    pub fn validate_info_table(&mut self, infotable_name: &str) -> DagReport {
+        self.get_info_table_as_info_table(infotable_name)
+            .validate_dag()
+    }
+
+    pub fn list_info_tables(&mut self) -> Vec<String> {
+        self.storage.list_info_tables()
+    }
+
+    pub fn get_table_merkle_tree(&mut self, infotable_name: &str) -> MerkleTree {
+        MerkleTree::from_table_lines(&self.storage.get_info_table(infotable_name))
+    }
+
+    ///
+    /// Returns the Merkle root of the table's triple ids (see MerkleTree), equal for two tables
+    /// with the same triples in any order.
+    ///
+    pub fn get_table_root(&mut self, infotable_name: &str) -> String {
+        self.get_table_merkle_tree(infotable_name).root()
+    }
+
+    ///
+    /// Returns the proof that the triple is in the table, to be checked against the table root
+    /// with MerkleProof::verify() or verify_inclusion(), or None if the table lacks the triple.
+    ///
+    pub fn get_inclusion_proof(&mut self, infotable_name: &str, triple_id: &str) -> Option<MerkleProof> {
+        self.get_table_merkle_tree(infotable_name).proof(triple_id)
+    }
+
+    ///
+    /// Returns the Merkle root over the roots of all tables of the current space (see
+    /// MerkleTree::space_root()).
+    ///
+    pub fn get_space_root(&mut self) -> String {
+        let tables: Vec<(String, MerkleTree)> = self.list_info_tables()
+            .into_iter()
+            .map(|x| {
+                let tree = self.get_table_merkle_tree(&x);
+                (x, tree)
+            })
+            .collect();
+        MerkleTree::space_root(tables)
+    }
+
+    ///
+    /// Syncs the current space with the current space of other: every table of either side gets
+    /// the triples (by id) only the other side has. As triples are content addressed this merge
+    /// has no conflicts. With dry_run nothing is written and the report tells what would be
+    /// added.
+    ///
+    pub fn sync_with<U: TripleStore>(&mut self, other: &mut TripleFacade<U>, dry_run: bool) -> SyncReport {
+        let here_tables = self.list_info_tables();
+        let there_tables = other.list_info_tables();
+        let mut tables: Vec<String> = here_tables.iter().chain(there_tables.iter()).cloned().collect();
+        tables.sort();
+        tables.dedup();
+
+        let mut report = SyncReport { dry_run, ..SyncReport::default() };
+        for table in tables {
+            //only read tables a side has, so a dry run does not create any
+            let here = if here_tables.contains(&table) { self.storage.get_info_table(&table) } else { String::new() };
+            let there = if there_tables.contains(&table) { other.storage.get_info_table(&table) } else { String::new() };
+            let to_there = Self::missing_lines(&here, &there);
+            let to_here = Self::missing_lines(&there, &here);
+
+            report.tables.insert(table.clone(), TableSync { added_here: to_here.len(), added_there: to_there.len() });
+            if !dry_run {
+                if !to_here.is_empty() {
+                    self.storage.append_many(&table, to_here);
+                }
+                if !to_there.is_empty() {
+                    other.storage.append_many(&table, to_there);
+                }
+            }
+        }
+        report
+    }
+
+    ///
+    /// Returns the patch turning the current space into the current space of newer, e.g. the
+    /// same space at a later point or on another node.
+    ///
+    pub fn diff_to<U: TripleStore>(&mut self, newer: &mut TripleFacade<U>) -> SpacePatch {
+        let old_tables = self.list_info_tables();
+        let new_tables = newer.list_info_tables();
+        let mut tables: Vec<String> = old_tables.iter().chain(new_tables.iter()).cloned().collect();
+        tables.sort();
+        tables.dedup();
+
+        let mut patch = SpacePatch::default();
+        for table in tables {
+            let old = if old_tables.contains(&table) { self.storage.get_info_table(&table) } else { String::new() };
+            let new = if new_tables.contains(&table) { newer.storage.get_info_table(&table) } else { String::new() };
+            let table_patch = TablePatch::between(&old, &new);
+            if !table_patch.is_empty() {
+                patch.tables.insert(table, table_patch);
+            }
+        }
+        patch
+    }
+
+    ///
+    /// Returns the lines of from whose triple id is not in to, once per id.
+    ///
+    fn missing_lines(from: &str, to: &str) -> Vec<String> {
+        let mut seen: HashSet<&str> = to
+            .lines()
+            .map(|x| x.split_once(' ').map_or(x, |(id, _)| id))
+            .collect();
+        from
+            .lines()
+            .filter(|x| seen.insert(x.split_once(' ').map_or(x, |(id, _)| id)))
+            .map(|x| x.to_string())
+            .collect()
+    }
+}
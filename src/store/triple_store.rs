@@ -1,18 +1,120 @@
 
+use std::time::SystemTime;
+
+///
+/// What a store can tell about the state of a table file: its modification time, length and
+/// inode. Modification times alone are too coarse to notice quick writes, a length changes with
+/// every append and an inode with every file replaced by a rename. Stamps of several files (see
+/// join()) compare part by part.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TableStamp {
+    parts: Vec<Option<(SystemTime, u64, u64)>>,
+}
+
+impl TableStamp {
+    pub fn new(modified: SystemTime, len: u64, inode: u64) -> Self {
+        TableStamp { parts: vec![Some((modified, len, inode))] }
+    }
+
+    ///
+    /// Joins the stamps of the files a table is made of, None if none of them has one.
+    ///
+    pub fn join<I: IntoIterator<Item = Option<TableStamp>>>(stamps: I) -> Option<TableStamp> {
+        let parts: Vec<Option<(SystemTime, u64, u64)>> = stamps
+            .into_iter()
+            .flat_map(|x| x.map_or(vec![None], |x| x.parts))
+            .collect();
+        parts.iter().any(|x| x.is_some()).then_some(TableStamp { parts })
+    }
+}
+
+///
+/// Info tables are append only logs of triple lines. Stores keep every appended line in append
+/// order, so a triple appended a second time is returned twice by get_info_table() and
+/// select_from_info_table().
+///
 pub trait TripleStore {
 
     fn append_info_table(&mut self, infotable_name: &str, infotriple: &str);
 
+    fn append_many<I: IntoIterator<Item = String>>(&mut self, infotable_name: &str, infotriples: I)
+    where
+        Self: Sized,
+    {
+        infotriples
+            .into_iter()
+            .for_each(|x| self.append_info_table(infotable_name, &x));
+    }
+
     fn get_info_table(&mut self, infotable_name: &str) -> String;
 
+    ///
+    /// Returns the lines of the triples whose id1 or id2 is exactly where_id, in append order.
+    ///
     fn select_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> String;
 
+    fn get_triple(&mut self, infotable_name: &str, triple_id: &str) -> Option<String>;
+
+    fn contains_triple(&mut self, infotable_name: &str, triple_id: &str) -> bool {
+        self.get_triple(infotable_name, triple_id).is_some()
+    }
+
 //    fn append_space_info_table(&mut self, infotable_name: &str, infotriple: &str, space_id: String);
 
 //    fn select_from_space_info_table(&mut self, infotable_name: &str, where_id: &str) -> String;
 
     fn clear_infotable(&mut self, infotable_name: String);
 
+    ///
+    /// Replaces all triples of the table with infotriples. Stores replace the table in one step,
+    /// so readers and crashes see either the old or the new triples. The default clears the table
+    /// and appends the triples one by one, which is not atomic.
+    ///
+    fn replace_info_table(&mut self, infotable_name: &str, infotriples: Vec<String>) {
+        self.clear_infotable(infotable_name.to_string());
+        infotriples
+            .iter()
+            .for_each(|x| self.append_info_table(infotable_name, x));
+    }
+
+    ///
+    /// Returns the names of the info tables in the current space, sorted. A table is listed once
+    /// it has been appended to or replaced, and stays listed after it is cleared. The default
+    /// lists no tables, for stores that cannot enumerate theirs; sync_spaces() then has nothing
+    /// to send from them.
+    ///
+    fn list_info_tables(&mut self) -> Vec<String> {
+        Vec::new()
+    }
+
+    ///
+    /// Returns the state of the table, if the store can tell, so caches can notice writes by
+    /// others: a different stamp means the table was written.
+    ///
+    fn table_stamp(&mut self, _infotable_name: &str) -> Option<TableStamp> {
+        None
+    }
+
+    ///
+    /// Returns the table's rewrite generation, if the store keeps one: a number that changes
+    /// whenever the table is rewritten (cleared, replaced or patched) rather than appended to, so
+    /// versions taken before (see TripleFacade::get_info_table_version()) can tell they no longer
+    /// apply. Stores without generations cannot tell, and versions of their tables only check
+    /// the number of lines.
+    ///
+    fn table_generation(&mut self, _infotable_name: &str) -> Option<u64> {
+        None
+    }
+
+    ///
+    /// Returns the number of lines the table had at time, if the store records when lines were
+    /// appended.
+    ///
+    fn version_at(&mut self, _infotable_name: &str, _time: SystemTime) -> Option<u64> {
+        None
+    }
+
     fn set_tmp_space_id(&mut self, space_id: String);
 
     fn revert_space_id(&mut self);
@@ -1,6 +1,27 @@
 use std::{fs, path::Path};
-use super::triple_store::TripleStore; 
-use ig_tools::file_tools;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, BufReader};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use super::triple_store::{TableStamp, TripleStore};
+use super::table_file::{self, SyncPolicy, TableLock};
+use super::table_format::{self, TableFormat};
+use super::table_compression::{self, Compression};
+use super::table_encryption::{self, SpaceKey};
+use super::store_error::StoreError;
+use crate::{SpacePatch, TablePatch, PatchConflict};
+
+///
+/// How a table file is stored, from the outermost layer (encryption) to the innermost (format).
+/// inner_empty is true if there is nothing below the encryption yet, as the table then takes the
+/// compression and format of the space.
+///
+struct TableLayers {
+    file_empty: bool,
+    encrypted: bool,
+    inner_empty: bool,
+    compressed: bool,
+    format: TableFormat,
+}
 
 #[derive(Clone)]
 pub struct TripleStoreFS {
@@ -8,37 +29,813 @@ pub struct TripleStoreFS {
     table_folder_name: String,
     org_space: String,
     tmp_space: Option<String>,
+    sync_policy: SyncPolicy,
+    lock_timeout: Duration,
+    recovered_tables: HashSet<String>,
+    space_keys: HashMap<String, SpaceKey>,
 }
 
 impl TripleStoreFS {
     pub fn new(space_id: String) -> Self {
-        let table_folder = "infospace/files/info_tables/";
-        let space_folder = "infospace/files/spaces/";
+        TripleStoreFS::new_in("infospace", space_id)
+    }
+
+    ///
+    /// Creates a store with another root folder than the default infospace, e.g. to sync with
+    /// a copy of an infospace.
+    ///
+    pub fn new_in(root: &str, space_id: String) -> Self {
+        let table_folder = format!("{}/files/info_tables/", root);
+        let space_folder = format!("{}/files/spaces/", root);
         let table_folder_name = "info_tables";
         
         let _ = fs::create_dir_all(table_folder);
-        let _ = fs::create_dir_all(space_folder);
+        let _ = fs::create_dir_all(&space_folder);
         TripleStoreFS {
-            space_folder: space_folder.to_string(),
+            space_folder,
             table_folder_name: table_folder_name.to_string(),
             org_space: space_id.clone(),
             tmp_space: Option::None,
+            sync_policy: SyncPolicy::default(),
+            lock_timeout: Duration::from_secs(10),
+            recovered_tables: HashSet::new(),
+            space_keys: HashMap::new(),
+        }
+    }
+
+    ///
+    /// Sets when writes are flushed to disk (see SyncPolicy), SyncPolicy::Never unless set.
+    ///
+    pub fn set_sync_policy(&mut self, sync_policy: SyncPolicy) {
+        self.sync_policy = sync_policy;
+    }
+
+    pub fn get_sync_policy(&self) -> SyncPolicy {
+        self.sync_policy
+    }
+
+    ///
+    /// Sets how long to wait for other processes holding a conflicting lock on a table before
+    /// giving up with a lock contention error.
+    ///
+    pub fn set_lock_timeout(&mut self, lock_timeout: Duration) {
+        self.lock_timeout = lock_timeout;
+    }
+
+    fn has_partial_line(&mut self, table_name: String) -> Result<bool, StoreError> {
+        let _lock = self.lock_table(table_name.clone(), false)?;
+        let path = self.get_table_path(table_name);
+        if table_compression::is_compressed_file(&path).unwrap_or(false)
+            || table_encryption::is_encrypted_file(&path).unwrap_or(false) {
+            return Ok(table_compression::has_partial_segment(&path).unwrap_or(true));
+        }
+        Ok(match TableFormat::detect_file(&path) {
+            Ok(TableFormat::Binary) => table_format::has_partial_record(&path).unwrap_or(true),
+            _ => table_file::has_partial_line(&path).unwrap_or(true),
+        })
+    }
+
+    fn space_dir(&mut self) -> String {
+        let space_id = self.get_space_id();
+        format!("{}{}", self.space_folder, space_id.trim())
+    }
+
+    ///
+    /// Returns the path of a file kept alongside a table (e.g. its length commitment), in the
+    /// space's table_meta folder so it is never listed as a table.
+    ///
+    fn table_meta_path(&mut self, table_name: &str, extension: &str) -> String {
+        format!("{}/table_meta/{}.{}", self.space_dir(), table_name, extension)
+    }
+
+    fn format_path(&mut self) -> String {
+        format!("{}/table_format", self.space_dir())
+    }
+
+    ///
+    /// Returns the format new tables of the current space are written in. Existing tables keep
+    /// the format they have (see convert_space()), and are read in either format.
+    ///
+    pub fn get_table_format(&mut self) -> TableFormat {
+        fs::read_to_string(self.format_path())
+            .ok()
+            .and_then(|x| TableFormat::from_name(&x))
+            .unwrap_or_default()
+    }
+
+    ///
+    /// Sets the format new tables of the current space are written in.
+    ///
+    pub fn set_table_format(&mut self, format: TableFormat) {
+        let _ = fs::create_dir_all(self.space_dir());
+        table_file::replace_file(&self.format_path(), format!("{}\n", format.name()).as_bytes(), self.sync_policy)
+            .expect("something went wrong writing table format file");
+    }
+
+    ///
+    /// Sets the table format of the current space and converts all its tables to it, one table at
+    /// a time under an exclusive lock.
+    ///
+    pub fn convert_space(&mut self, format: TableFormat) {
+        self.set_table_format(format);
+        self.rewrite_tables();
+    }
+
+    fn compression_path(&mut self) -> String {
+        format!("{}/table_compression", self.space_dir())
+    }
+
+    ///
+    /// Returns whether new tables of the current space are compressed. Existing tables keep
+    /// their compression (see compress_space()), and are read either way.
+    ///
+    pub fn get_compression(&mut self) -> Compression {
+        fs::read_to_string(self.compression_path())
+            .ok()
+            .and_then(|x| Compression::from_name(&x))
+            .unwrap_or_default()
+    }
+
+    ///
+    /// Sets whether new tables of the current space are compressed.
+    ///
+    pub fn set_compression(&mut self, compression: Compression) {
+        let _ = fs::create_dir_all(self.space_dir());
+        table_file::replace_file(&self.compression_path(), format!("{}\n", compression.name()).as_bytes(), self.sync_policy)
+            .expect("something went wrong writing table compression file");
+    }
+
+    ///
+    /// Sets the compression of the current space and (de)compresses all its tables, one table at
+    /// a time under an exclusive lock. Called with the current compression, it recompacts the
+    /// segments that appends have added to each table into one.
+    ///
+    pub fn compress_space(&mut self, compression: Compression) {
+        self.set_compression(compression);
+        self.rewrite_tables();
+    }
+
+    fn encryption_path(&mut self) -> String {
+        format!("{}/table_encryption", self.space_dir())
+    }
+
+    ///
+    /// Adds the key of an encrypted space, needed for every read and write of the space, also
+    /// when it is used as a guest space.
+    ///
+    pub fn add_space_key(&mut self, space_id: String, key: SpaceKey) {
+        self.space_keys.insert(space_id, key);
+    }
+
+    pub fn is_space_encrypted(&mut self) -> bool {
+        Path::new(&self.encryption_path()).is_file()
+    }
+
+    ///
+    /// Returns the key added for the current space, checked against the key check in the space's
+    /// encryption file.
+    ///
+    fn space_key(&mut self) -> Result<SpaceKey, StoreError> {
+        let space_id = self.get_space_id();
+        let key = *self.space_keys.get(&space_id).ok_or(StoreError::MissingKey { space_id })?;
+        let path = self.encryption_path();
+        if let Ok(key_check) = fs::read(&path) {
+            if !table_encryption::verify_key(&key_check, &key) {
+                return Err(StoreError::WrongKey { path });
+            }
+        }
+        Ok(key)
+    }
+
+    fn write_key_check(&mut self, key: &SpaceKey) {
+        let _ = fs::create_dir_all(self.space_dir());
+        let key_check = table_encryption::key_check(key).unwrap_or_else(|e| panic!("{}", e));
+        table_file::replace_file(&self.encryption_path(), &key_check, self.sync_policy)
+            .expect("something went wrong writing table encryption file");
+    }
+
+    ///
+    /// Encrypts the current space with key: new tables are encrypted and all existing tables are
+    /// rewritten encrypted, one table at a time under an exclusive lock.
+    ///
+    pub fn encrypt_space(&mut self, key: SpaceKey) {
+        let space_id = self.get_space_id();
+        self.add_space_key(space_id, key);
+        self.write_key_check(&key);
+        self.rewrite_tables();
+    }
+
+    ///
+    /// Decrypts all tables of the current space and stops encrypting new ones.
+    ///
+    pub fn decrypt_space(&mut self) {
+        self.space_key().unwrap_or_else(|e| panic!("{}", e));
+        let _ = fs::remove_file(self.encryption_path());
+        self.rewrite_tables();
+    }
+
+    ///
+    /// Re-encrypts all tables of the current space with new_key, replacing the key added for the
+    /// space. Tables are rewritten one at a time, so an interrupted rotation is finished by calling
+    /// it again with the same new key (and the old key still added).
+    ///
+    pub fn rotate_space_key(&mut self, new_key: SpaceKey) {
+        let old_key = self.space_key().unwrap_or_else(|e| panic!("{}", e));
+        for table_name in self.list_info_tables() {
+            self.create_tablefile_if_not_there(table_name.clone());
+            let _lock = self.lock_table(table_name.clone(), true).unwrap_or_else(|e| panic!("{}", e));
+            let path = self.get_table_path(table_name.clone());
+            let content = fs::read(&path).expect("something went wrong reading info table file");
+            if !table_encryption::is_encrypted(&content) {
+                continue;
+            }
+            let context = self.encryption_context(&table_name);
+            let inner = match table_encryption::decrypt(&content, &old_key, &context, &path) {
+                Ok(inner) => inner,
+                //rotated before an interruption, maybe before its commitment
+                Err(StoreError::WrongKey { .. }) if table_encryption::decrypt(&content, &new_key, &context, &path).is_ok() => {
+                    self.commit_table_len(&table_name, content.len() as u64, Some(new_key))
+                        .unwrap_or_else(|e| panic!("{}", e));
+                    continue;
+                },
+                Err(e) => panic!("{}", e),
+            };
+            let old_len = content.len() as u64;
+            let content = table_encryption::encrypt(&inner, &new_key, &context).unwrap_or_else(|e| panic!("{}", e));
+            self.replace_table_file(&table_name, &content, Some(new_key)).unwrap_or_else(|e| panic!("{}", e));
+            self.restamp_append_times(&table_name, old_len, content.len() as u64).unwrap_or_else(|e| panic!("{}", e));
+        }
+        self.write_key_check(&new_key);
+        let space_id = self.get_space_id();
+        self.add_space_key(space_id, new_key);
+    }
+
+    ///
+    /// Rewrites all tables of the current space in the space's format, compression and
+    /// encryption.
+    ///
+    fn rewrite_tables(&mut self) {
+        for table_name in self.list_info_tables() {
+            self.create_tablefile_if_not_there(table_name.clone());
+            let _lock = self.lock_table(table_name.clone(), true).unwrap_or_else(|e| panic!("{}", e));
+            let text = self.read_table_file(&table_name).unwrap_or_else(|e| panic!("{}", e));
+            let old_len = fs::metadata(self.get_table_path(table_name.clone())).map_or(0, |x| x.len());
+            let content = self.encode_table(&table_name, text).unwrap_or_else(|e| panic!("{}", e));
+            let key = self.table_key(&content).unwrap_or_else(|e| panic!("{}", e));
+            self.replace_table_file(&table_name, &content, key).unwrap_or_else(|e| panic!("{}", e));
+            self.restamp_append_times(&table_name, old_len, content.len() as u64).unwrap_or_else(|e| panic!("{}", e));
+        }
+    }
+
+    ///
+    /// Encodes the whole text of a table in the space's format, compression and encryption.
+    ///
+    fn encode_table(&mut self, table_name: &str, text: String) -> Result<Vec<u8>, StoreError> {
+        let mut content = match self.get_table_format() {
+            TableFormat::Text => text.into_bytes(),
+            TableFormat::Binary => table_format::text_to_binary(&text)?,
+        };
+        if let Compression::Zstd(level) = self.get_compression() {
+            content = table_compression::compress(&content, level)?;
+        }
+        if self.is_space_encrypted() {
+            let key = self.space_key()?;
+            let context = self.encryption_context(table_name);
+            content = table_encryption::encrypt(&content, &key, &context)?;
+        }
+        Ok(content)
+    }
+
+    ///
+    /// Applies the patch to the tables of the current space, all or nothing as far as conflicts
+    /// go: the tables are locked (in name order) and checked first, and if any of them conflicts
+    /// with the patch nothing is written, no table is created and StoreError::PatchConflicts has
+    /// all conflicts. Otherwise the patched content of every table is written to a temp file
+    /// before the first table is replaced, all while the locks are held, so readers never see a
+    /// table half patched and an error while encoding or writing leaves every table as it was.
+    /// The append times of the patched tables start over (see version_at()).
+    ///
+    pub fn apply_patch(&mut self, patch: &SpacePatch) -> Result<(), StoreError> {
+        let mut tables: Vec<(&String, &TablePatch, Option<String>)> = patch.tables
+            .iter()
+            .filter(|(_, x)| !x.is_empty())
+            .map(|(table_name, table_patch)| (table_name, table_patch, None))
+            .collect();
+        let mut locks: Vec<TableLock> = Vec::new();
+        let mut conflicts: Vec<PatchConflict> = Vec::new();
+        for (table_name, table_patch, text) in tables.iter_mut() {
+            //tables that are not there are checked as empty, without making their file
+            if Path::new(&self.get_table_path(table_name.to_string())).is_file() {
+                locks.push(self.lock_table(table_name.to_string(), true)?);
+                *text = Some(self.read_table_file(table_name)?);
+            }
+            conflicts.extend(table_patch.conflicts(table_name, text.as_deref().unwrap_or("")));
+        }
+        if !conflicts.is_empty() {
+            return Err(StoreError::PatchConflicts(conflicts));
+        }
+
+        //another process may have made a missing table since, so it is checked again once locked
+        fs::create_dir_all(format!("{}/{}", self.space_dir(), self.table_folder_name))?;
+        for (table_name, table_patch, text) in tables.iter_mut().filter(|(_, _, text)| text.is_none()) {
+            locks.push(self.lock_table(table_name.to_string(), true)?);
+            if Path::new(&self.get_table_path(table_name.to_string())).is_file() {
+                let current = self.read_table_file(table_name)?;
+                conflicts.extend(table_patch.conflicts(table_name, &current));
+                *text = Some(current);
+            }
+        }
+        if !conflicts.is_empty() {
+            return Err(StoreError::PatchConflicts(conflicts));
+        }
+
+        let mut written: Vec<(String, String, u64, Option<SpaceKey>, usize)> = Vec::new();
+        for (table_name, table_patch, text) in tables {
+            let text = table_patch.apply_to(text.as_deref().unwrap_or(""));
+            let lines = text.lines().count();
+            match self.write_tmp_table(table_name, text) {
+                Ok((tmp_path, len, key)) => written.push((table_name.to_string(), tmp_path, len, key, lines)),
+                Err(e) => {
+                    written.iter().for_each(|(_, tmp_path, ..)| {
+                        let _ = fs::remove_file(tmp_path);
+                    });
+                    return Err(e);
+                },
+            }
+        }
+        for (table_name, tmp_path, len, key, lines) in written {
+            self.rename_table_file(&table_name, &tmp_path, len, key)?;
+            self.reset_append_times(&table_name, lines, len)?;
+        }
+        Ok(())
+    }
+
+    ///
+    /// Encodes the text of a table and writes it to a temp file next to the table file. Returns
+    /// the temp file's path, the length of the content and the key to commit that length with.
+    ///
+    fn write_tmp_table(&mut self, table_name: &str, text: String) -> Result<(String, u64, Option<SpaceKey>), StoreError> {
+        let content = if text.is_empty() { Vec::new() } else { self.encode_table(table_name, text)? };
+        let key = self.table_key(&content)?;
+        let path = self.get_table_path(table_name.to_string());
+        let tmp_path = table_file::write_tmp_file(&path, &content, self.sync_policy)?;
+        Ok((tmp_path, content.len() as u64, key))
+    }
+
+    ///
+    /// Finds out how the table file is stored.
+    ///
+    fn table_layers(&mut self, table_name: &str) -> Result<TableLayers, StoreError> {
+        let path = &self.get_table_path(table_name.to_string());
+        let file_empty = fs::metadata(path).map(|x| x.len() == 0).unwrap_or(true);
+        let encrypted = if file_empty { self.is_space_encrypted() } else { table_encryption::is_encrypted_file(path)? };
+
+        //the first segment below the encryption, or the start of the file
+        let head = if file_empty {
+            None
+        } else if encrypted {
+            let context = self.encryption_context(table_name);
+            Some(table_encryption::read_first_segment(path, &self.space_key()?, &context)?)
+        } else if table_compression::is_compressed_file(path)? {
+            Some(table_compression::compressed_header())
+        } else {
+            Some(Vec::new())
+        };
+        let inner_empty = head.as_ref().is_none_or(|x| encrypted && x.is_empty());
+        if inner_empty {
+            return Ok(TableLayers {
+                file_empty,
+                encrypted,
+                inner_empty,
+                compressed: self.get_compression() != Compression::None,
+                format: self.get_table_format(),
+            });
+        }
+
+        let head = head.unwrap_or_default();
+        let (compressed, format) = if encrypted && table_compression::is_compressed(&head) {
+            let mut first: Vec<u8> = Vec::new();
+            table_compression::for_each_segment(&mut &head[..], |x| {
+                first = x;
+                false
+            })?;
+            (true, TableFormat::detect(&first))
+        } else if encrypted {
+            (false, TableFormat::detect(&head))
+        } else if table_compression::is_compressed(&head) {
+            (true, TableFormat::detect(&table_compression::read_first_segment(path)?))
+        } else {
+            (false, TableFormat::detect_file(path)?)
+        };
+        Ok(TableLayers { file_empty, encrypted, inner_empty, compressed, format })
+    }
+
+    ///
+    /// Encodes lines to append to a table in the table's format, compression and encryption,
+    /// where an empty table takes the settings of the space. Should be called while holding the
+    /// table's exclusive lock.
+    ///
+    fn encode_layers(&mut self, table_name: &str, lines: &str) -> Result<Vec<u8>, StoreError> {
+        let layers = self.table_layers(table_name)?;
+
+        let mut content = match layers.format {
+            TableFormat::Text => lines.as_bytes().to_vec(),
+            TableFormat::Binary => {
+                let mut payload = if layers.inner_empty { table_format::binary_header() } else { Vec::new() };
+                payload.extend(table_format::encode_records(lines)?);
+                payload
+            },
+        };
+        if layers.compressed {
+            let level = match self.get_compression() {
+                Compression::Zstd(level) => level,
+                Compression::None => table_compression::DEFAULT_ZSTD_LEVEL,
+            };
+            content = if layers.inner_empty {
+                table_compression::compress(&content, level)?
+            } else {
+                table_compression::compress_segment(&content, level)?
+            };
+        }
+        if layers.encrypted {
+            let key = self.space_key()?;
+            let context = self.encryption_context(table_name);
+            content = if layers.file_empty {
+                table_encryption::encrypt(&content, &key, &context)?
+            } else {
+                let len = fs::metadata(self.get_table_path(table_name.to_string()))?.len();
+                table_encryption::encrypt_segment(&content, &key, &context, len)?
+            };
+        }
+        Ok(content)
+    }
+
+    ///
+    /// Decodes the content of a table file of the current space to text lines, whatever its
+    /// encryption, compression and format. Encrypted content is checked against the table's
+    /// length commitment.
+    ///
+    pub fn decode_table_file(&mut self, table_name: &str, content: Vec<u8>) -> Result<String, StoreError> {
+        let commitment_path = self.table_meta_path(table_name, "len");
+        let commitment = match fs::read(&commitment_path) {
+            Ok(commitment) => Some(commitment),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        let content = if table_encryption::is_encrypted(&content) || commitment.is_some() {
+            let key = self.space_key()?;
+            let context = self.encryption_context(table_name);
+            let path = self.get_table_path(table_name.to_string());
+            table_encryption::check_commitment(content.len() as u64, commitment.as_deref(), &key, &context, &path)?;
+            if table_encryption::is_encrypted(&content) {
+                table_encryption::decrypt(&content, &key, &context, &path)?
+            } else {
+                content
+            }
+        } else {
+            content
+        };
+        table_compression::decode_table(content)
+    }
+
+    fn read_table_file(&mut self, table_name: &str) -> Result<String, StoreError> {
+        let content = fs::read(self.get_table_path(table_name.to_string()))?;
+        self.decode_table_file(table_name, content)
+    }
+
+    ///
+    /// What the frames of an encrypted table are authenticated with besides their offset, so they
+    /// cannot be moved to another space or table.
+    ///
+    fn encryption_context(&mut self, table_name: &str) -> String {
+        format!("{}/{}", self.get_space_id(), table_name)
+    }
+
+    ///
+    /// The key to commit the length of new table content with, if it is encrypted or the space
+    /// is.
+    ///
+    fn table_key(&mut self, content: &[u8]) -> Result<Option<SpaceKey>, StoreError> {
+        if table_encryption::is_encrypted(content) || self.is_space_encrypted() {
+            Ok(Some(self.space_key()?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    ///
+    /// Writes the length commitment of an encrypted table, or removes the commitment of a table
+    /// that is no longer encrypted (key None).
+    ///
+    fn commit_table_len(&mut self, table_name: &str, len: u64, key: Option<SpaceKey>) -> Result<(), StoreError> {
+        let path = self.table_meta_path(table_name, "len");
+        match key {
+            Some(key) => {
+                let context = self.encryption_context(table_name);
+                let commitment = table_encryption::commitment(len, &key, &context)?;
+                fs::create_dir_all(format!("{}/table_meta", self.space_dir()))?;
+                table_file::replace_file(&path, &commitment, self.sync_policy)?;
+            },
+            None => match fs::remove_file(&path) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+                _ => (),
+            },
+        }
+        Ok(())
+    }
+
+    ///
+    /// Replaces the content of a table file, keeping its length commitment (with key) in step.
+    /// Called with the table's exclusive lock.
+    ///
+    fn replace_table_file(&mut self, table_name: &str, content: &[u8], key: Option<SpaceKey>) -> Result<(), StoreError> {
+        let path = self.get_table_path(table_name.to_string());
+        let tmp_path = table_file::write_tmp_file(&path, content, self.sync_policy)?;
+        self.rename_table_file(table_name, &tmp_path, content.len() as u64, key)
+    }
+
+    ///
+    /// Renames a temp file with len bytes of new content over the table file, keeping its length
+    /// commitment (with key) in step. Called with the table's exclusive lock.
+    ///
+    fn rename_table_file(&mut self, table_name: &str, tmp_path: &str, len: u64, key: Option<SpaceKey>) -> Result<(), StoreError> {
+        let path = self.get_table_path(table_name.to_string());
+        //lower the commitment first, so neither the old nor the new content falls short of it
+        //if the process dies in between
+        if key.is_some() {
+            let old_len = fs::metadata(&path).map_or(0, |x| x.len());
+            self.commit_table_len(table_name, old_len.min(len), key)?;
+        }
+        table_file::rename_over(tmp_path, &path, self.sync_policy)?;
+        self.commit_table_len(table_name, len, key)
+    }
+
+    ///
+    /// Returns a copy of this store (with the same settings) working in another space. This is
+    /// what lets callers pick the space per call instead of switching the space of a shared store.
+    ///
+    pub fn for_space(&self, space_id: String) -> TripleStoreFS {
+        TripleStoreFS {
+            org_space: space_id,
+            tmp_space: Option::None,
+            ..self.clone()
+        }
+    }
+
+    ///
+    /// Takes an advisory lock on the table file, shared for reads and exclusive for writes and
+    /// clears, so several processes can work on the same infospace. Gives up with
+    /// StoreError::LockTimeout after the lock timeout (see set_lock_timeout()).
+    ///
+    pub fn lock_table(&mut self, table_name: String, exclusive: bool) -> Result<TableLock, StoreError> {
+        let path = self.get_table_path(table_name);
+        if exclusive {
+            table_file::lock_exclusive(&path, self.lock_timeout)
+        } else {
+            table_file::lock_shared(&path, self.lock_timeout)
+        }
+    }
+
+    ///
+    /// Returns the path of the file in table_meta/ recording the table's appends, one line per
+    /// append with the time in milliseconds since the Unix epoch, the number of lines the table
+    /// had after it, the length of the table file after it and the table's generation. The times
+    /// never go back, a clock set back records the time of the append before. The generation
+    /// changes when the table is rewritten (see TripleStore::table_generation()).
+    ///
+    pub fn append_times_path(&mut self, table_name: &str) -> String {
+        self.table_meta_path(table_name, "ts")
+    }
+
+    ///
+    /// Reads the records of the append times file, leaving out lines that do not parse.
+    ///
+    fn read_append_times(&mut self, table_name: &str) -> Result<Vec<AppendTime>, StoreError> {
+        let times = match fs::read_to_string(self.append_times_path(table_name)) {
+            Ok(times) => times,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(times.lines().filter_map(AppendTime::parse).collect())
+    }
+
+    ///
+    /// Reads the last record of the append times file, from its end only, so appends do not get
+    /// slower as the file grows.
+    ///
+    fn last_append_time(&mut self, table_name: &str) -> Result<Option<AppendTime>, StoreError> {
+        let line = table_file::read_last_line(&self.append_times_path(table_name))?;
+        Ok(line.as_deref().and_then(AppendTime::parse))
+    }
+
+    ///
+    /// Appends records to the append times file. They are not synced whatever the sync policy: a
+    /// record lost in a crash leaves the table longer than its last record, which version_at()
+    /// notices and the next append repairs (see record_append()).
+    ///
+    fn append_time_records(&mut self, table_name: &str, records: &[AppendTime]) -> Result<(), StoreError> {
+        let path = self.append_times_path(table_name);
+        fs::create_dir_all(format!("{}/table_meta", self.space_dir()))?;
+        if table_file::has_partial_line(&path)? {
+            table_file::recover_partial_line(&path, SyncPolicy::Never)?;
+        }
+        let lines: String = records.iter().map(|x| x.line()).collect();
+        table_file::append_lines(&path, lines.as_bytes(), SyncPolicy::Never)?;
+        Ok(())
+    }
+
+    ///
+    /// Records that lines were appended to the table just now, growing its file from len_before
+    /// to len_after. Called with the table locked. When the last record does not end at
+    /// len_before, because the table was written before append times were recorded or a crash
+    /// lost the record, the lines the table had before are counted and recorded first, so the
+    /// records are in step with the table again. That reads the table, but only once after such a
+    /// write.
+    ///
+    pub fn record_append(&mut self, table_name: &str, lines: usize, len_before: u64, len_after: u64) -> Result<(), StoreError> {
+        let last = self.last_append_time(table_name)?;
+        let at = AppendTime::now_after(last.as_ref());
+        let lines = lines as u64;
+        let (count, generation) = match last {
+            Some(last) if last.len == len_before => {
+                let record = AppendTime { at, lines: last.lines + lines, len: len_after, generation: last.generation };
+                return self.append_time_records(table_name, &[record]);
+            },
+            None if len_before == 0 => (0, 0),
+            //a lost record cannot be told from a rewrite, so versions taken before are given up
+            _ => (
+                (self.read_table_file(table_name)?.lines().count() as u64).saturating_sub(lines),
+                AppendTime::next_generation(last.as_ref(), at),
+            ),
+        };
+        let records = [
+            AppendTime { at, lines: count, len: len_before, generation },
+            AppendTime { at, lines: count + lines, len: len_after, generation },
+        ];
+        self.append_time_records(table_name, &records)
+    }
+
+    ///
+    /// Starts the append times of a rewritten table over with the lines it has now. Called with
+    /// the table locked.
+    ///
+    pub fn reset_append_times(&mut self, table_name: &str, lines: usize, len: u64) -> Result<(), StoreError> {
+        let path = self.append_times_path(table_name);
+        fs::create_dir_all(format!("{}/table_meta", self.space_dir()))?;
+        let last = self.last_append_time(table_name)?;
+        let at = AppendTime::now_after(last.as_ref());
+        let record = AppendTime { at, lines: lines as u64, len, generation: AppendTime::next_generation(last.as_ref(), at) };
+        //synced, unlike appends, as the records of the old table would not fit the new one
+        table_file::replace_file(&path, record.line().as_bytes(), self.sync_policy)?;
+        Ok(())
+    }
+
+    ///
+    /// Keeps the append times of a table rewritten with the same lines (in another format,
+    /// compression or key) in step with its new file length. Called with the table locked.
+    ///
+    fn restamp_append_times(&mut self, table_name: &str, len_before: u64, len_after: u64) -> Result<(), StoreError> {
+        match self.last_append_time(table_name)? {
+            Some(last) if last.len == len_before && len_before != len_after => {
+                let at = AppendTime::now_after(Some(&last));
+                self.append_time_records(table_name, &[AppendTime { len: len_after, at, ..last }])
+            },
+            _ => Ok(()),
         }
     }
 
     pub fn create_tablefile_if_not_there(&mut self, table_name: String) {
+        self.try_create_tablefile_if_not_there(table_name).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    pub fn try_create_tablefile_if_not_there(&mut self, table_name: String) -> Result<(), StoreError> {
         let mut space_dir = self.space_folder.clone();
         space_dir.push_str(&self.get_space_id());
         space_dir.push('/');
         space_dir.push_str(&self.table_folder_name);
         let _ = fs::create_dir_all(space_dir);
 
-        let path = self.get_table_path(table_name);
+        let path = self.get_table_path(table_name.clone());
+
+        //create_new, so a table another process has just created and appended to is never
+        //truncated
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(_) => println!("Creating file {}", path),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => (),
+            Err(e) => return Err(e.into()),
+        }
 
-        if !Path::new(&path).is_file() {
-            println!("Creating file {}", path);
-            let _ = fs::write(path, "");
+        //a crash in the middle of a write can leave a partial line, so check once per table, and
+        //only take the exclusive lock when there is something to recover
+        if self.recovered_tables.contains(&path) {
+            return Ok(());
+        }
+        if self.has_partial_line(table_name.clone())? {
+            let _lock = self.lock_table(table_name, true)?;
+            let recovered = if table_compression::is_compressed_file(&path).unwrap_or(false)
+                || table_encryption::is_encrypted_file(&path).unwrap_or(false) {
+                table_compression::recover_partial_segment(&path, self.sync_policy)
+            } else {
+                match TableFormat::detect_file(&path) {
+                    Ok(TableFormat::Binary) => table_format::recover_partial_record(&path, self.sync_policy),
+                    _ => table_file::recover_partial_line(&path, self.sync_policy),
+                }
+            };
+            match recovered {
+                Ok(Some(partial)) => eprintln!(
+                    "Warning: truncated partial line \"{}\" at the end of {}, it is kept in {}.partial",
+                    partial, path, path),
+                Ok(None) => (),
+                Err(e) => eprintln!("Warning: could not check {} for a partial line: {}", path, e),
+            }
         }
+        self.recovered_tables.insert(path);
+        Ok(())
+    }
+
+    ///
+    /// Like append_info_table(), but returning lock timeouts and other store errors instead of
+    /// panicking.
+    ///
+    pub fn try_append_info_table(&mut self, infotable_name: &str, infotriple: &str) -> Result<(), StoreError> {
+        self.try_append_many(infotable_name, vec![infotriple.to_string()])
+    }
+
+    pub fn try_append_many(&mut self, infotable_name: &str, infotriples: Vec<String>) -> Result<(), StoreError> {
+        if infotriples.is_empty() {
+            return Ok(());
+        }
+        let mut lines: String = String::new();
+        infotriples
+            .iter()
+            .for_each(|x|{
+                lines.push_str(x);
+                lines.push('\n');
+            });
+        self.try_create_tablefile_if_not_there(infotable_name.to_string())?;
+        let _lock = self.lock_table(infotable_name.to_string(), true)?;
+        let path = self.get_table_path(infotable_name.to_string());
+        let content = self.encode_layers(infotable_name, &lines)?;
+        let len = fs::metadata(&path)?.len();
+        let key = if table_encryption::is_encrypted(&content) || table_encryption::is_encrypted_file(&path)? {
+            Some(self.space_key()?)
+        } else {
+            None
+        };
+        if key.is_some() && len == 0 {
+            //commit the empty table first, so its commitment is never missing once it has frames
+            self.commit_table_len(infotable_name, 0, key)?;
+        }
+        //one buffered write to the end of the file
+        table_file::append_lines(&path, &content, self.sync_policy)?;
+        if key.is_some() {
+            self.commit_table_len(infotable_name, len + content.len() as u64, key)?;
+        }
+        self.record_append(infotable_name, infotriples.len(), len, len + content.len() as u64)
+    }
+
+    ///
+    /// Like get_info_table(), but returning lock timeouts and other store errors instead of
+    /// panicking.
+    ///
+    pub fn try_get_info_table(&mut self, infotable_name: &str) -> Result<String, StoreError> {
+        self.try_create_tablefile_if_not_there(infotable_name.to_string())?;
+        let _lock = self.lock_table(infotable_name.to_string(), false)?;
+        self.read_table_file(infotable_name)
+    }
+
+    ///
+    /// Like replace_info_table(), but returning lock timeouts and other store errors instead of
+    /// panicking. The new content is written to a temp file renamed over the table file.
+    ///
+    pub fn try_replace_info_table(&mut self, infotable_name: &str, infotriples: Vec<String>) -> Result<(), StoreError> {
+        let mut lines: String = String::new();
+        infotriples
+            .iter()
+            .for_each(|x|{
+                lines.push_str(x);
+                lines.push('\n');
+            });
+        self.try_create_tablefile_if_not_there(infotable_name.to_string())?;
+        let _lock = self.lock_table(infotable_name.to_string(), true)?;
+        let content = if lines.is_empty() { Vec::new() } else { self.encode_table(infotable_name, lines)? };
+        let key = self.table_key(&content)?;
+        self.replace_table_file(infotable_name, &content, key)?;
+        self.reset_append_times(infotable_name, infotriples.len(), content.len() as u64)
+    }
+
+    pub fn try_clear_infotable(&mut self, infotable_name: String) -> Result<(), StoreError> {
+        let path = self.get_table_path(infotable_name.clone());
+        if Path::new(&path).is_file() {
+            let _lock = self.lock_table(infotable_name.clone(), true)?;
+            println!("Clearing file {}", path);
+            let key = self.table_key(b"")?;
+            self.replace_table_file(&infotable_name, b"", key)?;
+            self.reset_append_times(&infotable_name, 0, 0)?;
+        } else {
+            println!("Cannot find file to clear {}", path);
+        }
+        Ok(())
     }
    
     pub fn get_table_path(&mut self, table_name: String) -> String {
@@ -75,50 +872,429 @@ impl TripleStore for TripleStoreFS {
 
 
     fn append_info_table(&mut self, infotable_name: &str, infotriple: &str) {
-        
-        //create if not exists
-        self.create_tablefile_if_not_there(infotable_name.to_string());
-        file_tools::append_ln_to_file(infotriple, &self.get_table_path(infotable_name.to_string()));
+        self.try_append_info_table(infotable_name, infotriple).unwrap_or_else(|e| panic!("{}", e));
     }
 
-    fn get_info_table(&mut self, infotable_name: &str) -> String {
-        self.create_tablefile_if_not_there(infotable_name.to_string());
+    fn append_many<I: IntoIterator<Item = String>>(&mut self, infotable_name: &str, infotriples: I) {
+        self.try_append_many(infotable_name, infotriples.into_iter().collect()).unwrap_or_else(|e| panic!("{}", e));
+    }
 
-        fs::read_to_string(
-        self.get_table_path(infotable_name.to_string()))
-        .expect("something wetn wrong reading info table text file")
+    fn get_info_table(&mut self, infotable_name: &str) -> String {
+        self.try_get_info_table(infotable_name).unwrap_or_else(|e| panic!("{}", e))
     }
 
     fn select_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> String {
         
         self.create_tablefile_if_not_there(infotable_name.to_string());
+        let _lock = self.lock_table(infotable_name.to_string(), false).unwrap_or_else(|e| panic!("{}", e));
 
-        fs::read_to_string(self.get_table_path(infotable_name.to_string()))    
-            .expect("something wetn wrong reading info table text file")
+        self.read_table_file(infotable_name)
+            .unwrap_or_else(|e| panic!("{}", e))
             .lines()
             .filter(|x| { 
                 #[cfg(test)]
                 println!("new{}",x);
-                x.split_once(' ')
-                .unwrap_or(("",""))
-                .1
-                .contains(where_id)
+                x.split(' ')
+                .skip(1)
+                .any(|id| id == where_id)
             })
             .fold("".to_string(),
                 |acc, y|
                 format!( "{}{}{}", acc, y, "\n"))
     }
 
+    fn get_triple(&mut self, infotable_name: &str, triple_id: &str) -> Option<String> {
+
+        self.create_tablefile_if_not_there(infotable_name.to_string());
+        let _lock = self.lock_table(infotable_name.to_string(), false).unwrap_or_else(|e| panic!("{}", e));
+
+        let path = self.get_table_path(infotable_name.to_string());
+        let layers = self.table_layers(infotable_name).unwrap_or_else(|e| panic!("{}", e));
+        if layers.compressed && !layers.encrypted && layers.format == TableFormat::Text {
+            //decompress segment by segment and stop at the segment with the first match
+            let mut found: Option<String> = None;
+            let file = fs::File::open(&path).expect("something went wrong opening info table file");
+            table_compression::for_each_segment(&mut BufReader::new(file), |x| {
+                found = String::from_utf8_lossy(&x)
+                    .lines()
+                    .find(|x| x.split_once(' ').is_some_and(|(id, _)| id == triple_id))
+                    .map(|x| x.to_string());
+                found.is_none()
+            }).unwrap_or_else(|e| panic!("{}", e));
+            return found;
+        }
+        if layers.format == TableFormat::Binary && !layers.encrypted && !layers.compressed {
+            let file = fs::File::open(&path).expect("something went wrong opening info table file");
+            return table_format::find_record(&mut BufReader::new(file), triple_id).unwrap_or_else(|e| panic!("{}", e));
+        }
+        if layers.encrypted || layers.compressed {
+            return self.read_table_file(infotable_name)
+                .unwrap_or_else(|e| panic!("{}", e))
+                .lines()
+                .find(|x| x.split_once(' ').is_some_and(|(id, _)| id == triple_id))
+                .map(|x| x.to_string());
+        }
+
+        //stream the lines and stop at the first match instead of reading the whole table
+        let file = fs::File::open(path)
+            .expect("something went wrong opening info table text file");
+        BufReader::new(file)
+            .lines()
+            .map(|x| x.expect("something went wrong reading info table text file"))
+            .find(|x| x.split_once(' ').is_some_and(|(id, _)| id == triple_id))
+    }
+
     fn clear_infotable(&mut self, infotable_name: String) {
+        self.try_clear_infotable(infotable_name).unwrap_or_else(|e| panic!("{}", e));
+    }
 
-        let path = self.get_table_path(infotable_name);
-        if Path::new(&path).is_file() {
-            println!("Clearing file {}", path);
-            let _ = fs::write(path, "");
-        } else {
-            println!("Cannot find file to clear {}", path);
+    fn replace_info_table(&mut self, infotable_name: &str, infotriples: Vec<String>) {
+        self.try_replace_info_table(infotable_name, infotriples).unwrap_or_else(|e| panic!("{}", e));
+    }
+
+    ///
+    /// Lists the table files of the current space, leaving out lock, quarantine and temp files.
+    ///
+    fn list_info_tables(&mut self) -> Vec<String> {
+        let dir = format!("{}/{}", self.space_dir(), self.table_folder_name);
+        let mut tables: Vec<String> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|x| x.ok())
+                .filter(|x| x.path().is_file())
+                .map(|x| x.file_name().to_string_lossy().to_string())
+                .filter(|x| ![".lock", ".partial", ".tmp"].iter().any(|suffix| x.ends_with(suffix)))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        tables.sort();
+        tables
+    }
+
+    fn table_stamp(&mut self, infotable_name: &str) -> Option<TableStamp> {
+        let metadata = fs::metadata(self.get_table_path(infotable_name.to_string())).ok()?;
+        Some(TableStamp::new(metadata.modified().ok()?, metadata.len(), inode(&metadata)))
+    }
+
+    ///
+    /// Returns the lines of the last append up to time from the table's append times (see
+    /// append_times_path()). Gives None for tables without append times, for tables written since
+    /// their last record (e.g. by a crash before the record), and for times before the table's
+    /// last rewrite if it had lines then.
+    ///
+    fn version_at(&mut self, infotable_name: &str, time: SystemTime) -> Option<u64> {
+        let millis = time.duration_since(UNIX_EPOCH).map_or(0, |x| x.as_millis());
+        let _lock = self.lock_table(infotable_name.to_string(), false).ok()?;
+        let times = self.read_append_times(infotable_name).ok()?;
+        let last = *times.last()?;
+        if fs::metadata(self.get_table_path(infotable_name.to_string())).ok()?.len() != last.len {
+            return None;
+        }
+        //records of earlier generations count lines of a table since rewritten
+        let times: Vec<&AppendTime> = times.iter().filter(|x| x.generation == last.generation).collect();
+        match times.iter().take_while(|x| x.at <= millis).last() {
+            Some(x) => Some(x.lines),
+            None if times[0].lines == 0 => Some(0),
+            None => None,
         }
     }
+
+    ///
+    /// Returns the generation of the table's last append time record, 0 for tables without
+    /// records.
+    ///
+    fn table_generation(&mut self, infotable_name: &str) -> Option<u64> {
+        let _lock = self.lock_table(infotable_name.to_string(), false).ok()?;
+        Some(self.last_append_time(infotable_name).ok()?.map_or(0, |x| x.generation))
+    }
+}
+
+///
+/// A record of the append times file of a table (see TripleStoreFS::append_times_path()).
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct AppendTime {
+    at: u128,
+    lines: u64,
+    len: u64,
+    generation: u64,
+}
+
+impl AppendTime {
+
+    fn parse(line: &str) -> Option<AppendTime> {
+        let mut fields = line.split(' ');
+        Some(AppendTime {
+            at: fields.next()?.parse().ok()?,
+            lines: fields.next()?.parse().ok()?,
+            len: fields.next()?.parse().ok()?,
+            generation: fields.next()?.parse().ok()?,
+        })
+    }
+
+    fn line(&self) -> String {
+        format!("{} {} {} {}\n", self.at, self.lines, self.len, self.generation)
+    }
+
+    ///
+    /// Returns a generation after the one of last, the time of the rewrite unless that is not
+    /// later, so generations differ across processes and restarts.
+    ///
+    fn next_generation(last: Option<&AppendTime>, at: u128) -> u64 {
+        (at as u64).max(last.map_or(0, |x| x.generation + 1))
+    }
+
+    ///
+    /// Returns the time now in milliseconds since the Unix epoch, or the time of last if the clock
+    /// has been set back since.
+    ///
+    fn now_after(last: Option<&AppendTime>) -> u128 {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |x| x.as_millis());
+        now.max(last.map_or(0, |x| x.at))
+    }
+}
+
+#[cfg(unix)]
+fn inode(metadata: &fs::Metadata) -> u64 {
+    std::os::unix::fs::MetadataExt::ino(metadata)
+}
+
+//without inodes, a replaced file is noticed through its modification time and length
+#[cfg(not(unix))]
+fn inode(_metadata: &fs::Metadata) -> u64 {
+    0
+}
+
+#[test]
+fn store_test_suite_test() {
+    let mut ts = TripleStoreFS::new("store_test_suite_space".to_string());
+    super::store_test_suite::run_all(&mut ts);
+}
+
+#[test]
+fn dyn_triple_store_test() {
+    let mut ts: Box<dyn TripleStore> = Box::new(TripleStoreFS::new("dyn_test_space".to_string()));
+    ts.clear_infotable("main_table".to_string());
+    ts.append_info_table("main_table", "id-a id1-a id2-a");
+    assert_eq!(ts.get_info_table("main_table"), "id-a id1-a id2-a\n");
+}
+
+#[test]
+fn binary_table_format_test() {
+
+    let infotable_name = String::from("main_table");
+    let triple_a = "cfe94de90878b723efdb311090465f2158798c3e9c21e82ac1582190290f756e 38a24bbddca56373b49d0452f5e485c11048c729d9230beb2fa41044a36e9791 5fe9374c5e9e27ebb78f8bf7cd78bbb23ee51e672dc54c603ec1c5b3eef33feb";
+    let triple_b = "004580ab93a9c16797e1a6ede5b0ab1949c253fb9fb7374d5e6088adf9b18f2d 2bac5b3da5fb1ecde9e3b433a91515e2efbc908010f461d0fc9014edd74b29f4 5fe9374c5e9e27ebb78f8bf7cd78bbb23ee51e672dc54c603ec1c5b3eef33feb";
+    let mut ts = TripleStoreFS::new("binary_format_test_space".to_string());
+    ts.set_table_format(TableFormat::Text);
+    ts.clear_infotable(infotable_name.clone());
+    ts.append_info_table(&infotable_name, triple_a);
+
+    ts.convert_space(TableFormat::Binary);
+    assert_eq!(ts.get_table_format(), TableFormat::Binary);
+    ts.append_many(&infotable_name, vec![triple_b.to_string()]);
+    let path = ts.get_table_path(infotable_name.clone());
+    assert_eq!(fs::metadata(&path).unwrap().len() as usize, table_format::HEADER_LEN + 2 * table_format::RECORD_LEN);
+
+    let all = format!("{}\n{}\n", triple_a, triple_b);
+    assert_eq!(ts.get_info_table(&infotable_name), all);
+    assert_eq!(ts.select_from_info_table(&infotable_name, "2bac5b3da5fb1ecde9e3b433a91515e2efbc908010f461d0fc9014edd74b29f4"), format!("{}\n", triple_b));
+    assert_eq!(ts.get_triple(&infotable_name, &triple_a[..64]), Some(triple_a.to_string()));
+    assert_eq!(ts.get_triple(&infotable_name, &triple_a[65..129]), None);
+
+    //ids a binary table cannot hold are refused and leave the table as it was
+    assert!(matches!(
+        ts.try_append_info_table(&infotable_name, "id-c id1-c id2-c"),
+        Err(crate::store_error::StoreError::Format(_))
+    ));
+    assert_eq!(ts.get_info_table(&infotable_name), all);
+
+    ts.convert_space(TableFormat::Text);
+    assert_eq!(fs::read_to_string(&path).unwrap(), all);
+}
+
+#[cfg(feature = "compression")]
+#[test]
+fn compressed_table_test() {
+
+    let infotable_name = String::from("main_table");
+    let mut ts = TripleStoreFS::new("compression_test_space".to_string());
+    ts.set_compression(Compression::None);
+    ts.clear_infotable(infotable_name.clone());
+    ts.append_info_table(&infotable_name, "id-a id1-a id2-a");
+
+    ts.compress_space(Compression::Zstd(3));
+    let path = ts.get_table_path(infotable_name.clone());
+    ts.append_info_table(&infotable_name, "id-b id-a id2-b");
+    ts.append_many(&infotable_name, vec!["id-c id1-c id-a".to_string()]);
+    assert!(table_compression::is_compressed_file(&path).unwrap());
+
+    assert_eq!(ts.get_info_table(&infotable_name), "id-a id1-a id2-a\nid-b id-a id2-b\nid-c id1-c id-a\n");
+    assert_eq!(ts.select_from_info_table(&infotable_name, "id-a"), "id-b id-a id2-b\nid-c id1-c id-a\n");
+    assert_eq!(ts.get_triple(&infotable_name, "id-b"), Some("id-b id-a id2-b".to_string()));
+    assert!(!ts.contains_triple(&infotable_name, "id-d"));
+
+    //every append added a segment, recompacting leaves one
+    let segments = |path: &str| {
+        let mut count = 0;
+        table_compression::for_each_segment(&mut BufReader::new(fs::File::open(path).unwrap()), |_| {
+            count += 1;
+            true
+        }).unwrap();
+        count
+    };
+    assert_eq!(segments(&path), 3);
+    ts.compress_space(Compression::Zstd(3));
+    assert_eq!(segments(&path), 1);
+    assert_eq!(ts.get_info_table(&infotable_name), "id-a id1-a id2-a\nid-b id-a id2-b\nid-c id1-c id-a\n");
+
+    ts.compress_space(Compression::None);
+    assert_eq!(fs::read_to_string(&path).unwrap(), "id-a id1-a id2-a\nid-b id-a id2-b\nid-c id1-c id-a\n");
+}
+
+#[cfg(feature = "encryption")]
+#[test]
+fn encrypted_space_test() {
+
+    let infotable_name = String::from("main_table");
+    let key: SpaceKey = [1u8; 32];
+    let new_key: SpaceKey = [2u8; 32];
+    let mut ts = TripleStoreFS::new("encryption_test_space".to_string());
+    ts.add_space_key("encryption_test_space".to_string(), key);
+    if ts.is_space_encrypted() {
+        ts.decrypt_space();
+    }
+    ts.clear_infotable(infotable_name.clone());
+    ts.append_info_table(&infotable_name, "id-a id1-a id2-a");
+
+    ts.encrypt_space(key);
+    ts.append_info_table(&infotable_name, "id-b id-a id2-b");
+    let path = ts.get_table_path(infotable_name.clone());
+    assert!(!fs::read(&path).unwrap().windows(4).any(|x| x == b"id-a"));
+    assert_eq!(ts.get_info_table(&infotable_name), "id-a id1-a id2-a\nid-b id-a id2-b\n");
+    assert_eq!(ts.get_triple(&infotable_name, "id-b"), Some("id-b id-a id2-b".to_string()));
+
+    //cutting off the last append, whole or in part, is caught
+    let before = fs::read(&path).unwrap();
+    ts.append_info_table(&infotable_name, "id-c id1-c id2-c");
+    let after = fs::read(&path).unwrap();
+    fs::write(&path, &before).unwrap();
+    assert!(matches!(ts.try_get_info_table(&infotable_name), Err(StoreError::Truncated{ .. })));
+    fs::write(&path, &after[..after.len() - 3]).unwrap();
+    assert!(matches!(ts.try_get_info_table(&infotable_name), Err(StoreError::Truncated{ .. })));
+    fs::write(&path, &after).unwrap();
+
+    ts.rotate_space_key(new_key);
+    assert_eq!(ts.select_from_info_table(&infotable_name, "id-a"), "id-b id-a id2-b\n");
+
+    //the old key and a missing key give clear errors
+    let mut other = TripleStoreFS::new("other_test_space".to_string());
+    other.add_space_key("encryption_test_space".to_string(), key);
+    other.set_tmp_space_id("encryption_test_space".to_string());
+    assert!(matches!(other.table_layers(&infotable_name), Err(StoreError::WrongKey{ .. })));
+    let mut other = TripleStoreFS::new("other_test_space".to_string());
+    other.set_tmp_space_id("encryption_test_space".to_string());
+    assert!(matches!(other.table_layers(&infotable_name), Err(StoreError::MissingKey{ .. })));
+
+    ts.decrypt_space();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "id-a id1-a id2-a\nid-b id-a id2-b\nid-c id1-c id2-c\n");
+}
+
+#[test]
+fn get_triple_test() {
+
+    let infotable_name = String::from("get_triple_table");
+    let mut ts = TripleStoreFS::new("get_triple_test_space".to_string());
+    ts.clear_infotable(infotable_name.clone());
+    ts.append_info_table(&infotable_name, "id-a id1-a id2-a");
+    ts.append_info_table(&infotable_name, "id-b id-a id2-b");
+
+    assert_eq!(ts.get_triple(&infotable_name, "id-b"), Some("id-b id-a id2-b".to_string()));
+    assert_eq!(ts.get_triple(&infotable_name, "id-a"), Some("id-a id1-a id2-a".to_string()));
+    assert!(ts.contains_triple(&infotable_name, "id-a"));
+    assert!(!ts.contains_triple(&infotable_name, "id1-a"));
+    assert!(!ts.contains_triple(&infotable_name, "id"));
+}
+
+#[test]
+fn append_many_test() {
+
+    let infotable_name = String::from("append_many_table");
+    let mut ts = TripleStoreFS::new("append_many_test_space".to_string());
+    ts.clear_infotable(infotable_name.clone());
+    ts.append_info_table(&infotable_name, "id-a id1-a id2-a");
+    ts.append_many(&infotable_name, vec!["id-b id1-b id2-b".to_string(), "id-c id1-c id2-c".to_string()]);
+    ts.append_many(&infotable_name, Vec::new());
+
+    assert_eq!(
+        ts.get_info_table(&infotable_name),
+        "id-a id1-a id2-a\nid-b id1-b id2-b\nid-c id1-c id2-c\n".to_string()
+    );
+}
+
+#[test]
+fn recover_partial_line_on_open_test() {
+
+    let infotable_name = String::from("partial_line_table");
+    let mut ts = TripleStoreFS::new("partial_line_test_space".to_string());
+    ts.create_tablefile_if_not_there(infotable_name.clone());
+    let path = ts.get_table_path(infotable_name.clone());
+    fs::write(&path, "id-a id1-a id2-a\nid-b id1-b").unwrap();
+
+    let mut ts = TripleStoreFS::new("partial_line_test_space".to_string());
+    assert_eq!(ts.get_sync_policy(), SyncPolicy::Never);
+    ts.set_sync_policy(SyncPolicy::Always);
+    ts.append_info_table(&infotable_name, "id-c id1-c id2-c");
+
+    assert_eq!(ts.get_info_table(&infotable_name), "id-a id1-a id2-a\nid-c id1-c id2-c\n".to_string());
+}
+
+#[test]
+fn lock_contention_across_processes_test() {
+
+    let infotable_name = String::from("lock_table");
+    let mut ts = TripleStoreFS::new("lock_test_space".to_string());
+    ts.create_tablefile_if_not_there(infotable_name.clone());
+    //outside info_tables/, so it is never listed as a table
+    let marker = format!("{}/lock_child_marker", ts.space_dir());
+
+    //the child process is this test run again, holding an exclusive lock for a while
+    if std::env::var("IG_TR_LOCK_CHILD").is_ok() {
+        let _lock = ts.lock_table(infotable_name, true).unwrap();
+        fs::write(&marker, "").unwrap();
+        std::thread::sleep(Duration::from_millis(1500));
+        return;
+    }
+
+    let _ = fs::remove_file(&marker);
+    let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "store::triple_store_fs::lock_contention_across_processes_test", "--nocapture"])
+        .env("IG_TR_LOCK_CHILD", "1")
+        .stdout(std::process::Stdio::null())
+        .spawn()
+        .unwrap();
+    while !Path::new(&marker).is_file() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let path = ts.get_table_path(infotable_name.clone());
+    let timeout = Duration::from_millis(100);
+    assert!(matches!(
+        table_file::lock_shared(&path, timeout),
+        Err(crate::store_error::StoreError::LockTimeout{ .. })
+    ));
+
+    //the error can be handled through the try_ functions
+    ts.set_lock_timeout(timeout);
+    assert!(matches!(
+        ts.try_append_info_table(&infotable_name, "id-a id1-a id2-a"),
+        Err(crate::store_error::StoreError::LockTimeout{ exclusive: true, .. })
+    ));
+
+    //a writer waits for the child to let go of the lock
+    ts.set_lock_timeout(Duration::from_secs(10));
+    ts.append_info_table(&infotable_name, "id-a id1-a id2-a");
+    assert!(child.wait().unwrap().success());
+    let _ = fs::remove_file(&marker);
 }
 
 //TODO: some day figure out a general way to setup test data files to ensure sameness and/or independence of test
@@ -143,4 +1319,75 @@ fn select_from_space_info_table_test() {
      assert_eq!(result_fn,result_goal);   
 }
 
+#[test]
+fn version_at_test() {
+    let mut ts = TripleStoreFS::new("version_at_test_space".to_string());
+    ts.clear_infotable("main_table".to_string());
+    let before = SystemTime::now();
+    std::thread::sleep(Duration::from_millis(5));
+    ts.append_info_table("main_table", "id-a id1-a id2-a");
+    std::thread::sleep(Duration::from_millis(5));
+    let between = SystemTime::now();
+    std::thread::sleep(Duration::from_millis(5));
+    ts.append_many("main_table", vec!["id-b id1-b id2-b".to_string(), "id-c id1-c id2-c".to_string()]);
+
+    assert_eq!(ts.version_at("main_table", before), Some(0));
+    assert_eq!(ts.version_at("main_table", between), Some(1));
+    assert_eq!(ts.version_at("main_table", SystemTime::now()), Some(3));
+    assert!(Path::new(&ts.append_times_path("main_table")).is_file());
+    assert_eq!(ts.list_info_tables().iter().filter(|x| x.starts_with("main_table")).count(), 1);
+
+    //lines written without a record (as by a crash before it) make the times unknown, the next
+    //append cannot tell them from a rewrite and starts a new generation
+    let generation = ts.table_generation("main_table");
+    let path = ts.get_table_path("main_table".to_string());
+    table_file::append_lines(&path, b"id-d id1-d id2-d\n", SyncPolicy::Never).unwrap();
+    assert_eq!(ts.version_at("main_table", SystemTime::now()), None);
+    std::thread::sleep(Duration::from_millis(5));
+    let between = SystemTime::now();
+    std::thread::sleep(Duration::from_millis(5));
+    ts.append_info_table("main_table", "id-e id1-e id2-e");
+    assert_ne!(ts.table_generation("main_table"), generation);
+    assert_eq!(ts.version_at("main_table", before), None);
+    assert_eq!(ts.version_at("main_table", between), None);
+    assert_eq!(ts.version_at("main_table", SystemTime::now()), Some(5));
+
+    //a rewrite with the same lines keeps the times, a replace starts them over
+    #[cfg(feature = "compression")]
+    {
+        let generation = ts.table_generation("main_table");
+        ts.compress_space(Compression::Zstd(3));
+        assert_eq!(ts.version_at("main_table", SystemTime::now()), Some(5));
+        assert_eq!(ts.table_generation("main_table"), generation);
+        ts.compress_space(Compression::None);
+    }
+    let generation = ts.table_generation("main_table");
+    let between = SystemTime::now();
+    std::thread::sleep(Duration::from_millis(5));
+    ts.replace_info_table("main_table", vec!["id-a id1-a id2-a".to_string()]);
+    assert_ne!(ts.table_generation("main_table"), generation);
+    assert_eq!(ts.version_at("main_table", between), None);
+    assert_eq!(ts.version_at("main_table", SystemTime::now()), Some(1));
+    ts.clear_infotable("main_table".to_string());
+    assert_eq!(ts.version_at("main_table", between), Some(0));
+}
+
+#[test]
+fn many_appends_times_test() {
+    let mut ts = TripleStoreFS::new("many_appends_test_space".to_string());
+    ts.clear_infotable("main_table".to_string());
+    for i in 0..300 {
+        ts.append_info_table("main_table", &format!("id-{} id1-{} id2-{}", i, i, i));
+    }
+    ts.append_many("main_table", (300..310).map(|i| format!("id-{} id1-{} id2-{}", i, i, i)).collect::<Vec<String>>());
 
+    //one record per append after the one of the clear, the last in step with the table
+    let times = ts.read_append_times("main_table").unwrap();
+    assert_eq!(times.len(), 302);
+    assert!(times.windows(2).all(|x| x[0].at <= x[1].at && x[0].lines < x[1].lines && x[0].len < x[1].len));
+    let last = ts.last_append_time("main_table").unwrap().unwrap();
+    assert_eq!(last, times[301]);
+    assert_eq!(last.lines, 310);
+    assert_eq!(last.len, fs::metadata(ts.get_table_path("main_table".to_string())).unwrap().len());
+    assert_eq!(ts.version_at("main_table", SystemTime::now()), Some(310));
+}
@@ -0,0 +1,299 @@
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use redb::{Database, MultimapTableDefinition, ReadableTable, ReadTransaction, TableDefinition, TableError, TableHandle, WriteTransaction};
+
+use super::triple_store::TripleStore;
+
+///
+/// A TripleStore on an embedded key-value database (redb). Per space and info table there is a
+/// log tree (sequence number -> triple) keeping the append order, a tree keyed by triple id for
+/// point lookups, and index trees for id1 and id2 used by select_from_info_table().
+///
+/// Like in the other stores, a triple appended a second time is kept as another line; the id tree
+/// points to its first line.
+///
+/// A database file can only be opened once, so stores for other spaces on the same file have to
+/// be made with for_space() rather than with new().
+///
+pub struct TripleStoreKv {
+    db: Arc<Database>,
+    org_space: String,
+    tmp_space: Option<String>,
+}
+
+type LogTree<'a> = TableDefinition<'a, u64, &'static str>;
+type IdTree<'a> = TableDefinition<'a, &'static str, u64>;
+type IndexTree<'a> = MultimapTableDefinition<'a, &'static str, u64>;
+
+impl TripleStoreKv {
+    pub fn new(space_id: String) -> Self {
+        TripleStoreKv::new_in("infospace/files/triples.redb", space_id)
+    }
+
+    ///
+    /// Opens (or creates) the database at db_path, so another root than the default infospace
+    /// can be used.
+    ///
+    pub fn new_in(db_path: &str, space_id: String) -> Self {
+        if let Some(dir) = Path::new(db_path).parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let db = Database::create(db_path).expect("something went wrong opening the triple database");
+        TripleStoreKv {
+            db: Arc::new(db),
+            org_space: space_id,
+            tmp_space: Option::None,
+        }
+    }
+
+    ///
+    /// Returns a store on the same database working in space_id.
+    ///
+    pub fn for_space(&self, space_id: String) -> Self {
+        TripleStoreKv {
+            db: self.db.clone(),
+            org_space: space_id,
+            tmp_space: Option::None,
+        }
+    }
+
+    fn tree_name(&mut self, infotable_name: &str, tree: &str) -> String {
+        format!("{}/{}/{}", Self::escape(&self.get_space_id()), Self::escape(infotable_name), tree)
+    }
+
+    ///
+    /// Escapes the '/' separating space, table and tree in tree names (and the '%' escaping it),
+    /// so space a/b with table c and space a with table b/c get trees of their own.
+    ///
+    fn escape(name: &str) -> String {
+        name.replace('%', "%25").replace('/', "%2F")
+    }
+
+    fn unescape(name: &str) -> String {
+        name.replace("%2F", "/").replace("%25", "%")
+    }
+
+    fn begin_read(&self) -> ReadTransaction {
+        self.db.begin_read().expect("something went wrong starting a triple database read")
+    }
+
+    ///
+    /// Reads the triples with the given sequence numbers (in that order) from the log tree.
+    ///
+    fn read_log<I: IntoIterator<Item = u64>>(&mut self, infotable_name: &str, seqs: Option<I>) -> String {
+        let log_name = self.tree_name(infotable_name, "log");
+        let txn = self.begin_read();
+        let log = match txn.open_table(LogTree::new(&log_name)) {
+            Ok(log) => log,
+            Err(TableError::TableDoesNotExist(_)) => return String::new(),
+            Err(e) => panic!("something went wrong opening info table in the triple database: {}", e),
+        };
+
+        let mut lines = String::new();
+        match seqs {
+            Some(seqs) => seqs
+                .into_iter()
+                .for_each(|seq|{
+                    if let Some(x) = log.get(seq).expect("something went wrong reading the triple database") {
+                        lines.push_str(x.value());
+                        lines.push('\n');
+                    }
+                }),
+            None => log
+                .iter()
+                .expect("something went wrong reading the triple database")
+                .for_each(|x|{
+                    let (_, x) = x.expect("something went wrong reading the triple database");
+                    lines.push_str(x.value());
+                    lines.push('\n');
+                }),
+        }
+        lines
+    }
+
+    ///
+    /// Appends triples to the log and the id and index trees in txn.
+    ///
+    fn write_triples<I: IntoIterator<Item = String>>(&mut self, txn: &WriteTransaction, infotable_name: &str, infotriples: I) {
+        let log_name = self.tree_name(infotable_name, "log");
+        let id_name = self.tree_name(infotable_name, "id");
+        let id1_name = self.tree_name(infotable_name, "id1");
+        let id2_name = self.tree_name(infotable_name, "id2");
+
+        let mut log = txn.open_table(LogTree::new(&log_name))
+            .expect("something went wrong opening info table in the triple database");
+        let mut ids = txn.open_table(IdTree::new(&id_name))
+            .expect("something went wrong opening info table in the triple database");
+        let mut id1s = txn.open_multimap_table(IndexTree::new(&id1_name))
+            .expect("something went wrong opening info table in the triple database");
+        let mut id2s = txn.open_multimap_table(IndexTree::new(&id2_name))
+            .expect("something went wrong opening info table in the triple database");
+
+        let first_seq = log.last()
+            .expect("something went wrong reading the triple database")
+            .map_or(0, |(seq, _)| seq.value() + 1);
+        for (seq, x) in (first_seq..).zip(infotriples) {
+            let mut fields = x.splitn(3, ' ');
+            let (id, id1, id2) = (fields.next().unwrap_or(""), fields.next().unwrap_or(""), fields.next().unwrap_or(""));
+            log.insert(seq, x.as_str()).expect("something went wrong writing to the triple database");
+            if ids.get(id).expect("something went wrong reading the triple database").is_none() {
+                ids.insert(id, seq).expect("something went wrong writing to the triple database");
+            }
+            id1s.insert(id1, seq).expect("something went wrong writing to the triple database");
+            id2s.insert(id2, seq).expect("something went wrong writing to the triple database");
+        }
+    }
+
+    ///
+    /// Deletes the trees of the table in txn and returns whether the table had a log tree.
+    ///
+    fn delete_trees(&mut self, txn: &WriteTransaction, infotable_name: &str) -> bool {
+        let log_name = self.tree_name(infotable_name, "log");
+        let id_name = self.tree_name(infotable_name, "id");
+        let id1_name = self.tree_name(infotable_name, "id1");
+        let id2_name = self.tree_name(infotable_name, "id2");
+
+        let existed = txn.delete_table(LogTree::new(&log_name)).expect("something went wrong clearing info table in the triple database");
+        txn.delete_table(IdTree::new(&id_name)).expect("something went wrong clearing info table in the triple database");
+        txn.delete_multimap_table(IndexTree::new(&id1_name)).expect("something went wrong clearing info table in the triple database");
+        txn.delete_multimap_table(IndexTree::new(&id2_name)).expect("something went wrong clearing info table in the triple database");
+        existed
+    }
+}
+
+impl TripleStore for TripleStoreKv {
+
+    //temporary space handling begin
+    fn set_tmp_space_id(&mut self, space_id: String) {
+       self.tmp_space = Option::Some(space_id);
+    }
+
+    fn revert_space_id(&mut self) {
+        self.tmp_space = Option::Some(self.org_space.clone());
+    }
+
+    fn get_space_id(&mut self) -> String {
+        self.tmp_space.clone().unwrap_or(self.org_space.clone())
+    }
+    //temporary space handling end
+
+
+    fn append_info_table(&mut self, infotable_name: &str, infotriple: &str) {
+        self.append_many(infotable_name, vec![infotriple.to_string()]);
+    }
+
+    fn append_many<I: IntoIterator<Item = String>>(&mut self, infotable_name: &str, infotriples: I) {
+        //the log and the id and index trees are committed together, so the trees never point to
+        //lines the log does not have
+        let txn = self.db.begin_write().expect("something went wrong starting a triple database write");
+        self.write_triples(&txn, infotable_name, infotriples);
+        txn.commit().expect("something went wrong committing to the triple database");
+    }
+
+    fn get_info_table(&mut self, infotable_name: &str) -> String {
+        self.read_log(infotable_name, None::<Vec<u64>>)
+    }
+
+    fn select_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> String {
+        let mut seqs: BTreeSet<u64> = BTreeSet::new();
+        for tree in ["id1", "id2"] {
+            let index_name = self.tree_name(infotable_name, tree);
+            let txn = self.begin_read();
+            let index = match txn.open_multimap_table(IndexTree::new(&index_name)) {
+                Ok(index) => index,
+                Err(TableError::TableDoesNotExist(_)) => return String::new(),
+                Err(e) => panic!("something went wrong opening info table in the triple database: {}", e),
+            };
+            index
+                .get(where_id)
+                .expect("something went wrong reading the triple database")
+                .for_each(|seq| {
+                    seqs.insert(seq.expect("something went wrong reading the triple database").value());
+                });
+        }
+        //sorted sequence numbers keep the append order
+        self.read_log(infotable_name, Some(seqs))
+    }
+
+    fn get_triple(&mut self, infotable_name: &str, triple_id: &str) -> Option<String> {
+        let id_name = self.tree_name(infotable_name, "id");
+        let txn = self.begin_read();
+        let seq = match txn.open_table(IdTree::new(&id_name)) {
+            Ok(ids) => ids.get(triple_id).expect("something went wrong reading the triple database")?.value(),
+            Err(TableError::TableDoesNotExist(_)) => return None,
+            Err(e) => panic!("something went wrong opening info table in the triple database: {}", e),
+        };
+        self.read_log(infotable_name, Some([seq]))
+            .strip_suffix('\n')
+            .map(|x| x.to_string())
+    }
+
+    fn clear_infotable(&mut self, infotable_name: String) {
+        let txn = self.db.begin_write().expect("something went wrong starting a triple database write");
+        if self.delete_trees(&txn, &infotable_name) {
+            //an empty log tree keeps the cleared table listed
+            self.write_triples(&txn, &infotable_name, Vec::new());
+        }
+        txn.commit().expect("something went wrong committing to the triple database");
+    }
+
+    fn replace_info_table(&mut self, infotable_name: &str, infotriples: Vec<String>) {
+        let txn = self.db.begin_write().expect("something went wrong starting a triple database write");
+        self.delete_trees(&txn, infotable_name);
+        self.write_triples(&txn, infotable_name, infotriples);
+        txn.commit().expect("something went wrong committing to the triple database");
+    }
+
+    fn list_info_tables(&mut self) -> Vec<String> {
+        let prefix = format!("{}/", Self::escape(&self.get_space_id()));
+        let mut tables: Vec<String> = self.begin_read()
+            .list_tables()
+            .expect("something went wrong reading the triple database")
+            .filter_map(|x| {
+                x.name()
+                    .strip_prefix(&prefix)
+                    .and_then(|x| x.strip_suffix("/log"))
+                    .map(Self::unescape)
+            })
+            .collect();
+        tables.sort();
+        tables
+    }
+}
+
+
+#[test]
+fn store_test_suite_kv_test() {
+    let mut ts = TripleStoreKv::new_in("infospace/files/store_test_suite.redb", "store_test_suite_kv_space".to_string());
+    super::store_test_suite::run_all(&mut ts);
+}
+
+#[test]
+fn kv_for_space_and_duplicates_test() {
+    let ts = TripleStoreKv::new_in("infospace/files/kv_for_space_test.redb", "kv_test_space".to_string());
+    let mut other = ts.for_space("kv_other_test_space".to_string());
+    other.clear_infotable("main_table".to_string());
+    other.append_many("main_table", vec!["id-a id1-a id2-a".to_string(), "id-a id1-a id2-a".to_string()]);
+    other.append_info_table("main_table", "id-a id1-a id2-a");
+
+    assert_eq!(other.get_info_table("main_table"), "id-a id1-a id2-a\nid-a id1-a id2-a\nid-a id1-a id2-a\n");
+    assert_eq!(other.select_from_info_table("main_table", "id2-a"), "id-a id1-a id2-a\nid-a id1-a id2-a\nid-a id1-a id2-a\n");
+    assert_eq!(other.get_triple("main_table", "id-a"), Some("id-a id1-a id2-a".to_string()));
+}
+
+#[test]
+fn kv_separator_in_names_test() {
+    let mut ts = TripleStoreKv::new_in("infospace/files/kv_separator_test.redb", "kv_sep/space".to_string());
+    let mut nested = ts.for_space("kv_sep".to_string());
+    ts.clear_infotable("table".to_string());
+    nested.clear_infotable("space/table".to_string());
+    ts.append_info_table("table", "id-a id1-a id2-a");
+    nested.append_info_table("space/table", "id-b id1-b id2-b");
+    assert_eq!(ts.get_info_table("table"), "id-a id1-a id2-a\n");
+    assert_eq!(nested.get_info_table("space/table"), "id-b id1-b id2-b\n");
+    assert_eq!(ts.list_info_tables(), vec!["table".to_string()]);
+    assert_eq!(nested.list_info_tables(), vec!["space/table".to_string()]);
+}
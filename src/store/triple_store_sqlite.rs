@@ -0,0 +1,194 @@
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use rusqlite::{params, Connection, OptionalExtension, Transaction};
+
+use super::triple_store::TripleStore;
+
+///
+/// A TripleStore keeping all spaces of a root in one SQLite database, with a space and an
+/// info_table column and indexes on id, id1 and id2. The seq column keeps the append order, so
+/// tables read back in the same order as from TripleStoreFS. The info_tables table keeps the
+/// names of the tables written, so cleared tables stay listed.
+///
+pub struct TripleStoreSqlite {
+    conn: Connection,
+    org_space: String,
+    tmp_space: Option<String>,
+}
+
+impl TripleStoreSqlite {
+    pub fn new(space_id: String) -> Self {
+        TripleStoreSqlite::new_in("infospace/files/triples.sqlite", space_id)
+    }
+
+    ///
+    /// Opens (or creates) the database at db_path, so another root than the default infospace
+    /// can be used.
+    ///
+    pub fn new_in(db_path: &str, space_id: String) -> Self {
+        if let Some(dir) = Path::new(db_path).parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        let conn = Connection::open(db_path).expect("something went wrong opening the triple database");
+        conn.busy_timeout(Duration::from_secs(10))
+            .expect("something went wrong configuring the triple database");
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS triples (
+                seq INTEGER PRIMARY KEY AUTOINCREMENT,
+                space TEXT NOT NULL,
+                info_table TEXT NOT NULL,
+                id TEXT NOT NULL,
+                id1 TEXT NOT NULL,
+                id2 TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS triples_id ON triples (space, info_table, id);
+            CREATE INDEX IF NOT EXISTS triples_id1 ON triples (space, info_table, id1);
+            CREATE INDEX IF NOT EXISTS triples_id2 ON triples (space, info_table, id2);
+            CREATE TABLE IF NOT EXISTS info_tables (
+                space TEXT NOT NULL,
+                info_table TEXT NOT NULL,
+                PRIMARY KEY (space, info_table)
+            );
+            INSERT OR IGNORE INTO info_tables (space, info_table) SELECT DISTINCT space, info_table FROM triples;")
+            .expect("something went wrong creating the triple database tables");
+        TripleStoreSqlite {
+            conn,
+            org_space: space_id,
+            tmp_space: Option::None,
+        }
+    }
+
+    fn split_triple(infotriple: &str) -> (&str, &str, &str) {
+        let mut fields = infotriple.splitn(3, ' ');
+        (
+            fields.next().unwrap_or(""),
+            fields.next().unwrap_or(""),
+            fields.next().unwrap_or(""),
+        )
+    }
+
+    fn insert_triples<I: IntoIterator<Item = String>>(tx: &Transaction, space: &str, infotable_name: &str, infotriples: I) {
+        tx.execute(
+            "INSERT OR IGNORE INTO info_tables (space, info_table) VALUES (?1, ?2)",
+            params![space, infotable_name])
+            .expect("something went wrong inserting into the triple database");
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO triples (space, info_table, id, id1, id2) VALUES (?1, ?2, ?3, ?4, ?5)")
+            .expect("something went wrong preparing triple database insert");
+        infotriples
+            .into_iter()
+            .for_each(|x|{
+                let (id, id1, id2) = Self::split_triple(&x);
+                stmt.execute(params![space, infotable_name, id, id1, id2])
+                    .expect("something went wrong inserting into the triple database");
+            });
+    }
+
+    fn query_lines(&mut self, sql: &str, infotable_name: &str, where_id: Option<&str>) -> String {
+        let space = self.get_space_id();
+        let mut stmt = self.conn.prepare_cached(sql)
+            .expect("something went wrong preparing triple database query");
+        let mut rows = match where_id {
+            Some(where_id) => stmt.query(params![space, infotable_name, where_id]),
+            None => stmt.query(params![space, infotable_name]),
+        }.expect("something went wrong querying the triple database");
+
+        let mut lines = String::new();
+        while let Some(row) = rows.next().expect("something went wrong reading the triple database") {
+            let (id, id1, id2): (String, String, String) = (row.get(0).unwrap(), row.get(1).unwrap(), row.get(2).unwrap());
+            lines.push_str(&format!("{} {} {}\n", id, id1, id2));
+        }
+        lines
+    }
+}
+
+impl TripleStore for TripleStoreSqlite {
+
+    //temporary space handling begin
+    fn set_tmp_space_id(&mut self, space_id: String) {
+       self.tmp_space = Option::Some(space_id);
+    }
+
+    fn revert_space_id(&mut self) {
+        self.tmp_space = Option::Some(self.org_space.clone());
+    }
+
+    fn get_space_id(&mut self) -> String {
+        self.tmp_space.clone().unwrap_or(self.org_space.clone())
+    }
+    //temporary space handling end
+
+
+    fn append_info_table(&mut self, infotable_name: &str, infotriple: &str) {
+        self.append_many(infotable_name, vec![infotriple.to_string()]);
+    }
+
+    fn append_many<I: IntoIterator<Item = String>>(&mut self, infotable_name: &str, infotriples: I) {
+        let space = self.get_space_id();
+        //one transaction, so readers see either none or all of the new triples
+        let tx = self.conn.transaction().expect("something went wrong starting a triple database transaction");
+        Self::insert_triples(&tx, &space, infotable_name, infotriples);
+        tx.commit().expect("something went wrong committing to the triple database");
+    }
+
+    fn get_info_table(&mut self, infotable_name: &str) -> String {
+        self.query_lines(
+            "SELECT id, id1, id2 FROM triples WHERE space = ?1 AND info_table = ?2 ORDER BY seq",
+            infotable_name, None)
+    }
+
+    fn select_from_info_table(&mut self, infotable_name: &str, where_id: &str) -> String {
+        self.query_lines(
+            "SELECT id, id1, id2 FROM triples WHERE space = ?1 AND info_table = ?2 AND (id1 = ?3 OR id2 = ?3) ORDER BY seq",
+            infotable_name, Some(where_id))
+    }
+
+    fn get_triple(&mut self, infotable_name: &str, triple_id: &str) -> Option<String> {
+        let space = self.get_space_id();
+        self.conn.query_row(
+            "SELECT id, id1, id2 FROM triples WHERE space = ?1 AND info_table = ?2 AND id = ?3 ORDER BY seq LIMIT 1",
+            params![space, infotable_name, triple_id],
+            |row| Ok(format!("{} {} {}", row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?)))
+            .optional()
+            .expect("something went wrong querying the triple database")
+    }
+
+    fn clear_infotable(&mut self, infotable_name: String) {
+        let space = self.get_space_id();
+        self.conn.execute(
+            "DELETE FROM triples WHERE space = ?1 AND info_table = ?2",
+            params![space, infotable_name])
+            .expect("something went wrong clearing info table in the triple database");
+    }
+
+    fn replace_info_table(&mut self, infotable_name: &str, infotriples: Vec<String>) {
+        let space = self.get_space_id();
+        let tx = self.conn.transaction().expect("something went wrong starting a triple database transaction");
+        tx.execute(
+            "DELETE FROM triples WHERE space = ?1 AND info_table = ?2",
+            params![space, infotable_name])
+            .expect("something went wrong clearing info table in the triple database");
+        Self::insert_triples(&tx, &space, infotable_name, infotriples);
+        tx.commit().expect("something went wrong committing to the triple database");
+    }
+
+    fn list_info_tables(&mut self) -> Vec<String> {
+        let space = self.get_space_id();
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT info_table FROM info_tables WHERE space = ?1 ORDER BY info_table")
+            .expect("something went wrong preparing triple database query");
+        stmt.query_map(params![space], |row| row.get::<_, String>(0))
+            .expect("something went wrong querying the triple database")
+            .map(|x| x.expect("something went wrong reading the triple database"))
+            .collect()
+    }
+}
+
+
+#[test]
+fn store_test_suite_sqlite_test() {
+    let mut ts = TripleStoreSqlite::new("store_test_suite_sqlite_space".to_string());
+    super::store_test_suite::run_all(&mut ts);
+}
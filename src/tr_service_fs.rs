@@ -1,9 +1,16 @@
 
+use std::io;
+use std::net::{TcpListener, ToSocketAddrs};
+
+use ig_tools::hashing_tools;
+
 use crate::triple_store::TripleStore;
-use crate::{InfoTriple, InfoTable};
+use crate::{InfoTriple, InfoTable, SyncReport, MerkleProof, SpacePatch, TableVersion};
 use crate::tr_director::TrDirector;
 use crate::triple_facade::TripleFacade;
 use crate::triple_store_fs::TripleStoreFS;
+use crate::store_error::StoreError;
+use crate::tr_sync_fs;
 
 #[derive(Clone)]
 pub struct TrServiceFS {
@@ -23,6 +30,18 @@ impl TrServiceFS {
         }
     }
 
+    ///
+    /// Like new(), but with the spaces under root instead of the default infospace.
+    ///
+    pub fn new_in(root: &str, space_id: String) -> Self {
+
+         TrServiceFS {
+            director: TrDirector::new(TripleFacade{ storage: TripleStoreFS::new_in(root, space_id.clone())}),
+            space_id: space_id.clone(),
+            org_space_id: space_id.clone(),
+        }
+    }
+
     pub fn set_tmp_space_id(&mut self, space_id: String) {
         self.space_id = space_id;
     }
@@ -43,10 +62,116 @@ impl TrServiceFS {
         self.director.create_triple(id1, id2)
     }
 
+    pub fn create_infotriples(&mut self, pairs: Vec<(String, String)>) -> Vec<InfoTriple> {
+        self.director.create_triples(pairs)
+    }
+
+    ///
+    /// Like get_all_info_triples_from_info_table(), but returning lock timeouts and other store
+    /// errors instead of panicking.
+    ///
+    pub fn try_get_all_info_triples_from_info_table(&mut self, infotable_name: String) -> Result<Vec<InfoTriple>, StoreError> {
+        Ok(self.director.triples.storage.try_get_info_table(&infotable_name)?
+            .lines()
+            .map(|x| InfoTriple::from(x.to_string()))
+            .collect())
+    }
+
+    ///
+    /// Like create_infotriples(), but returning lock timeouts and other store errors instead of
+    /// panicking. Nothing is stored when it fails.
+    ///
+    pub fn try_create_infotriples(&mut self, pairs: Vec<(String, String)>) -> Result<Vec<InfoTriple>, StoreError> {
+        let its: Vec<InfoTriple> = pairs
+            .iter()
+            .map(|(id1, id2)| InfoTriple::from(hashing_tools::concat_n_hash(id1.as_str(), id2.as_str())))
+            .collect();
+        self.director.triples.storage.try_append_many("main_table", its.iter().map(|x| x.to_tuple_string()).collect())?;
+        Ok(its)
+    }
+
+    pub fn create_symmetric_infotriple(&mut self, id1: String, id2: String) -> InfoTriple {
+        self.director.create_symmetric_triple(id1, id2)
+    }
+
+    pub fn find_triple(&mut self, id1: String, id2: String) -> Option<InfoTriple> {
+        self.director.find_triple(id1, id2)
+    }
+
+    pub fn find_triple_in_table_list(&mut self, id1: String, id2: String, table_list: Vec<String>) -> Option<InfoTriple> {
+        self.director.find_triple_in_table_list(id1, id2, table_list)
+    }
+
+    pub fn get_triple(&mut self, infotable_name: String, triple_id: String) -> Option<InfoTriple> {
+        self.director.get_triple(infotable_name, triple_id)
+    }
+
+    pub fn contains_triple(&mut self, infotable_name: String, triple_id: String) -> bool {
+        self.director.contains_triple(infotable_name, triple_id)
+    }
+
     pub fn clear_infotable(&mut self, infotable_name: String) {
         self.director.clear_infotable(infotable_name);
     }
 
+    ///
+    /// Syncs all info tables of this space with the space of other, which may be in another root,
+    /// so both end up with the union of their triples. With dry_run only the report is made.
+    ///
+    pub fn sync_with(&mut self, other: &mut TrServiceFS, dry_run: bool) -> SyncReport {
+        self.director.sync_with(&mut other.director, dry_run)
+    }
+
+    ///
+    /// Returns the patch turning this space into the space of newer.
+    ///
+    pub fn diff_to(&mut self, newer: &mut TrServiceFS) -> SpacePatch {
+        self.director.triples.diff_to(&mut newer.director.triples)
+    }
+
+    ///
+    /// Applies the patch to this space, or returns the conflicts (StoreError::PatchConflicts) or
+    /// another store error and leaves the space untouched (see TripleStoreFS::apply_patch()).
+    ///
+    pub fn apply_patch(&mut self, patch: &SpacePatch) -> Result<(), StoreError> {
+        self.director.triples.storage.apply_patch(patch)
+    }
+
+    ///
+    /// Serves the sync protocol of tr_sync_fs on listener, for max_connections connections or
+    /// forever with None.
+    ///
+    pub fn serve_sync(&mut self, listener: &TcpListener, max_connections: Option<usize>) -> io::Result<()> {
+        tr_sync_fs::serve(self, listener, max_connections)
+    }
+
+    ///
+    /// Syncs this space with the same space on the node serving at addr (see serve_sync()).
+    ///
+    pub fn sync_with_peer<A: ToSocketAddrs>(&mut self, addr: A, dry_run: bool) -> io::Result<SyncReport> {
+        tr_sync_fs::sync_with_peer(self, addr, dry_run)
+    }
+
+    pub fn get_info_table_version(&mut self, infotable_name: String) -> TableVersion {
+        self.director.get_info_table_version(infotable_name)
+    }
+
+    pub fn get_info_table_as_of(&mut self, infotable_name: String, version: TableVersion) -> Option<Vec<InfoTriple>> {
+        self.director.get_info_table_as_of(infotable_name, version)
+    }
+
+    pub fn get_table_root(&mut self, infotable_name: String) -> String {
+        self.director.get_table_root(infotable_name)
+    }
+
+    pub fn get_space_root(&mut self) -> String {
+        self.director.get_space_root()
+    }
+
+    pub fn get_inclusion_proof(&mut self, infotable_name: String, triple_id: String) -> Option<MerkleProof> {
+        self.director.get_inclusion_proof(infotable_name, triple_id)
+    }
+
 
     //guest functions
     pub fn get_all_info_triples_from_info_table_guest_space(&mut self, infotable_name: String, space_id: String) -> Vec<InfoTriple> {
@@ -77,6 +202,83 @@ impl TrServiceFS {
         result
     }
 
+    pub fn create_infotriples_guest_space(&mut self, pairs: Vec<(String, String)>, space_id: String) -> Vec<InfoTriple> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.create_infotriples(pairs);
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub fn create_symmetric_infotriple_guest_space(&mut self, id1: String, id2: String, space_id: String) -> InfoTriple {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.create_symmetric_infotriple(id1, id2);
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub fn find_triple_guest_space(&mut self, id1: String, id2: String, space_id: String) -> Option<InfoTriple> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.find_triple(id1, id2);
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub fn find_triple_in_table_list_guest_space(&mut self, id1: String, id2: String, table_list: Vec<String>, space_id: String) -> Option<InfoTriple> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.find_triple_in_table_list(id1, id2, table_list);
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub fn get_triple_guest_space(&mut self, infotable_name: String, triple_id: String, space_id: String) -> Option<InfoTriple> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.get_triple(infotable_name, triple_id);
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub fn contains_triple_guest_space(&mut self, infotable_name: String, triple_id: String, space_id: String) -> bool {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.contains_triple(infotable_name, triple_id);
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub fn get_info_table_as_of_guest_space(&mut self, infotable_name: String, version: TableVersion, space_id: String) -> Option<Vec<InfoTriple>> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.get_info_table_as_of(infotable_name, version);
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub fn get_table_root_guest_space(&mut self, infotable_name: String, space_id: String) -> String {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.get_table_root(infotable_name);
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub fn get_space_root_guest_space(&mut self, space_id: String) -> String {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.get_space_root();
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub fn get_inclusion_proof_guest_space(&mut self, infotable_name: String, triple_id: String, space_id: String) -> Option<MerkleProof> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.get_inclusion_proof(infotable_name, triple_id);
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
+    pub fn apply_patch_guest_space(&mut self, patch: &SpacePatch, space_id: String) -> Result<(), StoreError> {
+        self.director.triples.storage.set_tmp_space_id(space_id);
+        let result = self.apply_patch(patch);
+        self.director.triples.storage.revert_space_id();
+        result
+    }
+
     pub fn clear_infotable_guest_space(&mut self, infotable_name: String, space_id: String) {
         self.director.triples.storage.set_tmp_space_id(space_id);
         let result = self.clear_infotable(infotable_name);
@@ -84,3 +286,136 @@ impl TrServiceFS {
         result
     }
 }
+
+
+#[test]
+fn sync_with_test() {
+    let _ = std::fs::remove_dir_all("sync_test_root_a");
+    let _ = std::fs::remove_dir_all("sync_test_root_b");
+    let mut a = TrServiceFS::new_in("sync_test_root_a", "sync_test_space".to_string());
+    let mut b = TrServiceFS::new_in("sync_test_root_b", "sync_test_space".to_string());
+    let shared = a.create_infotriple("id1-s".to_string(), "id2-s".to_string());
+    b.create_infotriple("id1-s".to_string(), "id2-s".to_string());
+    let only_a = a.create_infotriple("id1-a".to_string(), "id2-a".to_string());
+    let only_b = b.create_infotriple("id1-b".to_string(), "id2-b".to_string());
+    b.director.triples.storage.append_info_table("b_table", "id-x id1-x id2-x");
+
+    let report = a.sync_with(&mut b, true);
+    assert!(report.dry_run);
+    assert_eq!(report.tables["main_table"].added_here, 1);
+    assert_eq!(report.tables["main_table"].added_there, 1);
+    assert_eq!(report.tables["b_table"].added_here, 1);
+    assert_eq!(a.get_all_info_triples_from_info_table("main_table".to_string()).len(), 2);
+    assert_eq!(a.director.triples.list_info_tables(), vec!["main_table".to_string()]);
+
+    let report = a.sync_with(&mut b, false);
+    assert_eq!(report.total_added_here(), 2);
+    assert_eq!(report.total_added_there(), 1);
+    for service in [&mut a, &mut b] {
+        assert_eq!(service.get_all_info_triples_from_info_table("main_table".to_string()).len(), 3);
+        for it in [&shared, &only_a, &only_b] {
+            assert!(service.contains_triple("main_table".to_string(), it.id.clone()));
+        }
+        assert!(service.contains_triple("b_table".to_string(), "id-x".to_string()));
+    }
+    assert!(a.sync_with(&mut b, false).was_in_sync());
+    assert_eq!(a.get_space_root(), b.get_space_root());
+    assert_eq!(a.get_table_root("main_table".to_string()), b.get_table_root("main_table".to_string()));
+    let proof = a.get_inclusion_proof("main_table".to_string(), only_b.id.clone()).unwrap();
+    assert!(proof.verify(&b.get_table_root("main_table".to_string())));
+
+    let _ = std::fs::remove_dir_all("sync_test_root_a");
+    let _ = std::fs::remove_dir_all("sync_test_root_b");
+}
+
+#[test]
+fn diff_and_apply_patch_test() {
+    let _ = std::fs::remove_dir_all("patch_test_root");
+    let mut old = TrServiceFS::new_in("patch_test_root", "patch_test_old_space".to_string());
+    let mut new = TrServiceFS::new_in("patch_test_root", "patch_test_new_space".to_string());
+    old.create_infotriple("id1-a".to_string(), "id2-a".to_string());
+    let removed = old.create_infotriple("id1-b".to_string(), "id2-b".to_string());
+    new.create_infotriple("id1-a".to_string(), "id2-a".to_string());
+    let added = new.create_infotriple("id1-c".to_string(), "id2-c".to_string());
+    new.director.triples.storage.append_info_table("new_table", "id-x id1-x id2-x");
+
+    let patch = old.diff_to(&mut new);
+    assert_eq!((patch.total_added(), patch.total_removed()), (2, 1));
+    let patch: SpacePatch = patch.to_string().parse().unwrap();
+
+    let version = old.get_info_table_version("main_table".to_string());
+    old.apply_patch(&patch).unwrap();
+    //the patch rewrote the table to as many lines, the old version must not read a prefix of it
+    assert_eq!(old.get_info_table_as_of("main_table".to_string(), version), None);
+    assert!(old.diff_to(&mut new).is_empty());
+    assert!(old.contains_triple("main_table".to_string(), added.id.clone()));
+    assert!(!old.contains_triple("main_table".to_string(), removed.id.clone()));
+
+    //the patched tables have their append times, starting over
+    assert_eq!(old.director.triples.storage.version_at("new_table", std::time::SystemTime::now()), Some(1));
+
+    //applied a second time every triple conflicts and nothing is written
+    let root = old.get_space_root();
+    match old.apply_patch(&patch) {
+        Err(StoreError::PatchConflicts(conflicts)) => assert_eq!(conflicts.len(), 3),
+        x => panic!("expected conflicts, got {:?}", x),
+    }
+    assert_eq!(old.get_space_root(), root);
+
+    //a conflicting patch does not make the tables it would add to
+    match old.apply_patch_guest_space(&patch, "patch_test_empty_space".to_string()) {
+        Err(StoreError::PatchConflicts(conflicts)) => assert_eq!(conflicts.len(), 1),
+        x => panic!("expected conflicts, got {:?}", x),
+    }
+    assert!(TripleStoreFS::new_in("patch_test_root", "patch_test_empty_space".to_string()).list_info_tables().is_empty());
+
+    let _ = std::fs::remove_dir_all("patch_test_root");
+}
+
+#[test]
+fn get_info_table_as_of_test() {
+    let mut service = TrServiceFS::new("as_of_test_space".to_string());
+    service.clear_infotable("main_table".to_string());
+    let a = service.create_infotriple("id1-a".to_string(), "id2-a".to_string());
+    let version = service.get_info_table_version("main_table".to_string());
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    let time = std::time::SystemTime::now();
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    service.create_infotriple("id1-b".to_string(), "id2-b".to_string());
+
+    let TableVersion::Seq { generation, lines } = version else { panic!("expected a seq version, got {:?}", version) };
+    assert_eq!(lines, 1);
+    assert_eq!(service.get_info_table_as_of("main_table".to_string(), version), Some(vec![a.clone()]));
+    assert_eq!(service.get_info_table_as_of("main_table".to_string(), TableVersion::Time(time)), Some(vec![a.clone()]));
+    assert_eq!(service.get_info_table_as_of("main_table".to_string(), TableVersion::Seq { generation, lines: 0 }), Some(vec![]));
+    assert_eq!(service.get_info_table_as_of("main_table".to_string(), TableVersion::Seq { generation, lines: 3 }), None);
+
+    //the append times of a rewritten table start at the rewrite, so earlier times are unknown
+    let line = format!("{} {} {}", a.id, a.id1, a.id2);
+    service.director.triples.storage.replace_info_table("main_table", vec![line]);
+    assert_eq!(service.get_info_table_as_of("main_table".to_string(), TableVersion::Time(time)), None);
+    assert_eq!(service.get_info_table_as_of("main_table".to_string(), TableVersion::Time(std::time::SystemTime::now())), Some(vec![a]));
+}
+
+#[test]
+fn try_create_infotriples_test() {
+    let mut service = TrServiceFS::new("try_test_space".to_string());
+    service.clear_infotable("main_table".to_string());
+    let created = service.try_create_infotriples(vec![("id1-a".to_string(), "id2-a".to_string())]).unwrap();
+    assert_eq!(service.try_get_all_info_triples_from_info_table("main_table".to_string()).unwrap(), created);
+
+    //flock locks belong to the open file, so a second lock in the same process contends too
+    let path = service.director.triples.storage.get_table_path("main_table".to_string());
+    let lock = crate::table_file::lock_exclusive(&path, std::time::Duration::from_secs(1)).unwrap();
+    service.director.triples.storage.set_lock_timeout(std::time::Duration::from_millis(50));
+    assert!(matches!(
+        service.try_create_infotriples(vec![("id1-b".to_string(), "id2-b".to_string())]),
+        Err(StoreError::LockTimeout{ .. })
+    ));
+    assert!(matches!(
+        service.try_get_all_info_triples_from_info_table("main_table".to_string()),
+        Err(StoreError::LockTimeout{ .. })
+    ));
+    drop(lock);
+    assert_eq!(service.try_get_all_info_triples_from_info_table("main_table".to_string()).unwrap(), created);
+}
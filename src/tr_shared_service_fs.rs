@@ -0,0 +1,135 @@
+use crate::{InfoTriple, InfoTable};
+use crate::tr_director::TrDirector;
+use crate::triple_facade::TripleFacade;
+use crate::triple_store_fs::TripleStoreFS;
+
+///
+/// A service handle that can be shared between threads (it is Send + Sync) as all methods take
+/// &self. Instead of switching the space of a mutable store, each call works on its own store for
+/// the space it needs, and the file locks of TripleStoreFS keep concurrent calls apart, letting
+/// readers run side by side.
+///
+#[derive(Clone)]
+pub struct TrSharedServiceFS {
+    store: TripleStoreFS,
+    pub space_id: String,
+}
+
+impl TrSharedServiceFS {
+
+    pub fn new(space_id: String) -> Self {
+        TrSharedServiceFS::from_store(TripleStoreFS::new(space_id.clone()), space_id)
+    }
+
+    ///
+    /// Creates a handle from a configured store (e.g. with a sync policy or lock timeout), where
+    /// space_id is the space used by the non guest methods.
+    ///
+    pub fn from_store(store: TripleStoreFS, space_id: String) -> Self {
+        TrSharedServiceFS { store, space_id }
+    }
+
+    fn director(&self, space_id: String) -> TrDirector<TripleStoreFS> {
+        TrDirector::new(TripleFacade::new(self.store.for_space(space_id)))
+    }
+
+    pub fn get_all_info_triples_from_info_table(&self, infotable_name: String) -> Vec<InfoTriple> {
+        self.get_all_info_triples_from_info_table_guest_space(infotable_name, self.space_id.clone())
+    }
+
+    pub fn get_n_flatten_all_from_table_list(&self, table_list: Vec<String>) -> InfoTable {
+        self.get_n_flatten_all_from_table_list_guest_space(table_list, self.space_id.clone())
+    }
+
+    pub fn create_infotriple(&self, id1: String, id2: String) -> InfoTriple {
+        self.create_infotriple_guest_space(id1, id2, self.space_id.clone())
+    }
+
+    pub fn create_infotriples(&self, pairs: Vec<(String, String)>) -> Vec<InfoTriple> {
+        self.create_infotriples_guest_space(pairs, self.space_id.clone())
+    }
+
+    pub fn create_symmetric_infotriple(&self, id1: String, id2: String) -> InfoTriple {
+        self.create_symmetric_infotriple_guest_space(id1, id2, self.space_id.clone())
+    }
+
+    pub fn find_triple(&self, id1: String, id2: String) -> Option<InfoTriple> {
+        self.find_triple_guest_space(id1, id2, self.space_id.clone())
+    }
+
+    pub fn get_triple(&self, infotable_name: String, triple_id: String) -> Option<InfoTriple> {
+        self.get_triple_guest_space(infotable_name, triple_id, self.space_id.clone())
+    }
+
+    pub fn contains_triple(&self, infotable_name: String, triple_id: String) -> bool {
+        self.contains_triple_guest_space(infotable_name, triple_id, self.space_id.clone())
+    }
+
+    pub fn clear_infotable(&self, infotable_name: String) {
+        self.clear_infotable_guest_space(infotable_name, self.space_id.clone())
+    }
+
+
+    //guest functions
+    pub fn get_all_info_triples_from_info_table_guest_space(&self, infotable_name: String, space_id: String) -> Vec<InfoTriple> {
+        self.director(space_id).get_all_info_triples_from_info_table(infotable_name)
+    }
+
+    pub fn get_n_flatten_all_from_table_list_guest_space(&self, table_list: Vec<String>, space_id: String) -> InfoTable {
+        self.director(space_id).get_n_flatten_all_from_table_list(table_list)
+    }
+
+    pub fn create_infotriple_guest_space(&self, id1: String, id2: String, space_id: String) -> InfoTriple {
+        self.director(space_id).create_triple(id1, id2)
+    }
+
+    pub fn create_infotriples_guest_space(&self, pairs: Vec<(String, String)>, space_id: String) -> Vec<InfoTriple> {
+        self.director(space_id).create_triples(pairs)
+    }
+
+    pub fn create_symmetric_infotriple_guest_space(&self, id1: String, id2: String, space_id: String) -> InfoTriple {
+        self.director(space_id).create_symmetric_triple(id1, id2)
+    }
+
+    pub fn find_triple_guest_space(&self, id1: String, id2: String, space_id: String) -> Option<InfoTriple> {
+        self.director(space_id).find_triple(id1, id2)
+    }
+
+    pub fn get_triple_guest_space(&self, infotable_name: String, triple_id: String, space_id: String) -> Option<InfoTriple> {
+        self.director(space_id).get_triple(infotable_name, triple_id)
+    }
+
+    pub fn contains_triple_guest_space(&self, infotable_name: String, triple_id: String, space_id: String) -> bool {
+        self.director(space_id).contains_triple(infotable_name, triple_id)
+    }
+
+    pub fn clear_infotable_guest_space(&self, infotable_name: String, space_id: String) {
+        self.director(space_id).clear_infotable(infotable_name)
+    }
+}
+
+
+#[test]
+fn shared_between_threads_test() {
+    fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+    let service = TrSharedServiceFS::new("shared_service_test_space".to_string());
+    assert_send_sync(&service);
+    service.clear_infotable("main_table".to_string());
+
+    std::thread::scope(|scope| {
+        for t in 0..4 {
+            let service = &service;
+            scope.spawn(move || {
+                for i in 0..10 {
+                    service.create_infotriple(format!("id1-{}", t), format!("id2-{}", i));
+                    let _ = service.get_all_info_triples_from_info_table("main_table".to_string());
+                }
+            });
+        }
+    });
+
+    assert_eq!(service.get_all_info_triples_from_info_table("main_table".to_string()).len(), 40);
+    assert!(service.find_triple("id1-3".to_string(), "id2-9".to_string()).is_some());
+    assert!(service.find_triple_guest_space("id1-3".to_string(), "id2-9".to_string(), "other_test_space".to_string()).is_none());
+}
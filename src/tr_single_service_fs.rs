@@ -18,6 +18,14 @@ use crate::{InfoTriple, InfoTable};
         TrServiceFS::new(space_id).director.create_triple(id1, id2);
     }
 
+    pub fn create_infotriples(space_id: String, pairs: Vec<(String, String)>) {
+        TrServiceFS::new(space_id).director.create_triples(pairs);
+    }
+
+    pub fn create_symmetric_infotriple(space_id: String, id1: String, id2: String) {
+        TrServiceFS::new(space_id).director.create_symmetric_triple(id1, id2);
+    }
+
     pub fn clear_infotable(space_id: String, infotable_name: String) {
         TrServiceFS::new(space_id).director.clear_infotable(infotable_name);
     }
@@ -0,0 +1,483 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::ops::Bound;
+use std::time::Duration;
+
+use ig_tools::hashing_tools;
+
+use crate::triple_store::TripleStore;
+use crate::tr_service_fs::TrServiceFS;
+use crate::{MerkleTree, SyncReport, TableSync};
+
+///
+/// A line based protocol to sync the info tables of a space between two nodes, each with its own
+/// TrServiceFS, over TCP. The client sends one command per line and the server answers each
+/// before the next is sent:
+///
+/// `HELLO ig_tr_sync 1 <space id>` -> `HELLO ig_tr_sync 1 <space id>`, the server then serves
+/// that space for the rest of the connection
+/// `ROOTS` -> `ROOT <table> <merkle root>` per table, then `END`
+/// `RANGE <table> <from> <to>` -> `FINGERPRINT <count> <merkle root>` of the ids in the range
+/// `IDS <table> <from> <to>` -> `ID <id>` per id in the range, then `END`
+/// `GET <table> <n>` and n ids, one per line -> `TRIPLE <triple>` per triple found, then `END`
+/// `PUT <table> <n>` and n triples, one per line -> `OK <number of triples added>`
+/// `BYE` ends the connection
+///
+/// Ranges go from an id (inclusive) to an id (exclusive), with - for no bound. Errors are
+/// answered with `ERROR <message>` and end the connection. Space ids and table names have to be
+/// valid_name()s, and every triple sent is checked against the hash of its id1 and id2; a triple
+/// sent for GET also has to have one of the ids asked for. Lines are at most MAX_LINE_LEN bytes,
+/// and a peer silent for longer than TIMEOUT is dropped.
+///
+/// Tables with equal roots are skipped. For the others the client compares fingerprints of id
+/// ranges, splitting ranges at its own median id, until a range matches or is small enough to
+/// list, so only the ids around the differences go over the wire.
+///
+pub const PROTOCOL: &str = "ig_tr_sync";
+pub const PROTOCOL_VERSION: u32 = 1;
+
+///
+/// Ranges with at most this many ids on either side are listed instead of split further.
+///
+const LIST_LIMIT: usize = 16;
+
+pub const MAX_LINE_LEN: usize = 1 << 20;
+
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+///
+/// Returns true if name can be used as a space id or table name by a peer: not empty, without
+/// path separators, .. or whitespace and control characters, so it stays inside the root.
+///
+pub fn valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.contains("..")
+        && !name.chars().any(|x| x == '/' || x == '\\' || x.is_whitespace() || x.is_control())
+}
+
+///
+/// Returns true if the triple is made of its id1 and id2 by hashing_tools::concat_n_hash().
+///
+fn valid_triple(infotriple: &str) -> bool {
+    let mut fields = infotriple.splitn(3, ' ');
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some(_), Some(id1), Some(id2)) => hashing_tools::concat_n_hash(id1, id2) == infotriple,
+        _ => false,
+    }
+}
+
+///
+/// Serves sync connections on listener with the spaces of service, one connection at a time, and
+/// returns after max_connections connections (or never with None). Failed accepts and broken
+/// peers are logged and do not end serving.
+///
+pub fn serve(service: &mut TrServiceFS, listener: &TcpListener, max_connections: Option<usize>) -> io::Result<()> {
+    let mut served = 0;
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                eprintln!("Warning: could not accept a sync connection: {}", e);
+                continue;
+            },
+        };
+        if let Err(e) = handle_connection(service, stream) {
+            eprintln!("Warning: sync connection ended with an error: {}", e);
+        }
+        served += 1;
+        if max_connections.is_some_and(|x| served >= x) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+pub fn handle_connection(service: &mut TrServiceFS, stream: TcpStream) -> io::Result<()> {
+    let mut peer = Peer::new(stream)?;
+    let hello = peer.read_line()?;
+    let space_id = match hello.strip_prefix(&format!("HELLO {} {} ", PROTOCOL, PROTOCOL_VERSION)) {
+        Some(space_id) if valid_name(space_id) => space_id.to_string(),
+        _ => {
+            let _ = peer.send(&format!("ERROR expected HELLO {} {} <space id>", PROTOCOL, PROTOCOL_VERSION));
+            return Err(protocol_error(format!("invalid handshake: {}", hello)));
+        },
+    };
+    peer.send(&format!("HELLO {} {} {}", PROTOCOL, PROTOCOL_VERSION, space_id))?;
+
+    service.director.triples.storage.set_tmp_space_id(space_id);
+    let result = ServerSession { service, ids: HashMap::new() }.run(&mut peer);
+    service.director.triples.storage.revert_space_id();
+    if let Err(e) = &result {
+        let _ = peer.send(&format!("ERROR {}", e));
+    }
+    result
+}
+
+///
+/// Syncs the current space of service with the same space on the node at addr, both ways. With
+/// dry_run only the ids are compared and the report tells what would be transferred.
+///
+pub fn sync_with_peer<A: ToSocketAddrs>(service: &mut TrServiceFS, addr: A, dry_run: bool) -> io::Result<SyncReport> {
+    let mut peer = Peer::new(TcpStream::connect(addr)?)?;
+    let space_id = service.director.triples.storage.get_space_id();
+    let hello = format!("HELLO {} {} {}", PROTOCOL, PROTOCOL_VERSION, space_id);
+    if peer.request(&hello)? != hello {
+        return Err(protocol_error("handshake answered with another space or version".to_string()));
+    }
+
+    let mut remote_roots: BTreeMap<String, String> = BTreeMap::new();
+    peer.send("ROOTS")?;
+    for line in peer.read_list("ROOT")? {
+        match line.split_once(' ') {
+            Some((table, root)) if valid_name(table) => remote_roots.insert(table.to_string(), root.to_string()),
+            _ => return Err(peer.reject(format!("invalid root: {}", line))),
+        };
+    }
+    let local_tables = service.director.triples.list_info_tables();
+    let tables: BTreeSet<String> = local_tables.iter().chain(remote_roots.keys()).cloned().collect();
+
+    let mut report = SyncReport { dry_run, ..SyncReport::default() };
+    for table in tables {
+        //only read tables this side has, so a dry run does not create any
+        let lines = if local_tables.contains(&table) {
+            service.director.triples.storage.get_info_table(&table)
+        } else {
+            String::new()
+        };
+        let local: HashMap<&str, &str> = lines
+            .lines()
+            .map(|x| (x.split_once(' ').map_or(x, |(id, _)| id), x))
+            .collect();
+        let local_ids: BTreeSet<String> = local.keys().map(|x| x.to_string()).collect();
+
+        let mut remote_only: Vec<String> = Vec::new();
+        let mut local_only: Vec<String> = Vec::new();
+        match remote_roots.get(&table) {
+            Some(root) if *root == MerkleTree::from_ids(local_ids.iter().cloned()).root() => {},
+            Some(_) => reconcile(&mut peer, &table, &local_ids, None, None, &mut remote_only, &mut local_only)?,
+            None => local_only = local_ids.iter().cloned().collect(),
+        }
+
+        report.tables.insert(table.clone(), TableSync { added_here: remote_only.len(), added_there: local_only.len() });
+        if dry_run {
+            continue;
+        }
+        if !remote_only.is_empty() {
+            peer.send_block(&format!("GET {} {}", table, remote_only.len()), &remote_only)?;
+            let triples = peer.read_list("TRIPLE")?;
+            let requested: BTreeSet<&str> = remote_only.iter().map(|x| x.as_str()).collect();
+            if let Some(bad) = triples.iter().find(|x| !valid_triple(x) || !requested.contains(x.split(' ').next().unwrap_or(""))) {
+                return Err(peer.reject(format!("invalid or unrequested triple: {}", bad)));
+            }
+            service.director.triples.storage.append_many(&table, triples);
+        }
+        if !local_only.is_empty() {
+            let triples: Vec<String> = local_only.iter().map(|x| local[x.as_str()].to_string()).collect();
+            peer.send_block(&format!("PUT {} {}", table, triples.len()), &triples)?;
+            peer.read_answer("OK")?;
+        }
+    }
+    peer.send("BYE")?;
+    Ok(report)
+}
+
+///
+/// Finds the ids only one side has in the range of table, comparing fingerprints and splitting
+/// the range at the local median id while both sides have many ids in it.
+///
+fn reconcile(
+    peer: &mut Peer,
+    table: &str,
+    local_ids: &BTreeSet<String>,
+    from: Option<&str>,
+    to: Option<&str>,
+    remote_only: &mut Vec<String>,
+    local_only: &mut Vec<String>,
+) -> io::Result<()> {
+    let mine = ids_in_range(local_ids, from, to);
+    let fingerprint = peer.request(&format!("RANGE {} {} {}", table, encode_bound(from), encode_bound(to)))?;
+    let (count, root) = fingerprint
+        .strip_prefix("FINGERPRINT ")
+        .and_then(|x| x.split_once(' '))
+        .and_then(|(count, root)| Some((count.parse::<usize>().ok()?, root)))
+        .ok_or_else(|| protocol_error(format!("invalid fingerprint: {}", fingerprint)))?;
+
+    if count == mine.len() && root == MerkleTree::from_ids(mine.iter().map(|x| x.to_string())).root() {
+        return Ok(());
+    }
+    if count == 0 {
+        local_only.extend(mine.into_iter().cloned());
+        return Ok(());
+    }
+    if mine.len() <= LIST_LIMIT || count <= LIST_LIMIT {
+        peer.send(&format!("IDS {} {} {}", table, encode_bound(from), encode_bound(to)))?;
+        let theirs: BTreeSet<String> = peer.read_list("ID")?.into_iter().collect();
+        remote_only.extend(theirs.iter().filter(|x| !local_ids.contains(*x)).cloned());
+        local_only.extend(mine.into_iter().filter(|x| !theirs.contains(*x)).cloned());
+        return Ok(());
+    }
+
+    let median = mine[mine.len() / 2].clone();
+    reconcile(peer, table, local_ids, from, Some(&median), remote_only, local_only)?;
+    reconcile(peer, table, local_ids, Some(&median), to, remote_only, local_only)
+}
+
+struct ServerSession<'a> {
+    service: &'a mut TrServiceFS,
+    ids: HashMap<String, BTreeSet<String>>,
+}
+
+impl ServerSession<'_> {
+
+    fn run(&mut self, peer: &mut Peer) -> io::Result<()> {
+        loop {
+            let command = peer.read_line()?;
+            let fields: Vec<&str> = command.split(' ').collect();
+            //checked before the table is looked up in storage
+            if let [_, table, ..] = fields.as_slice() {
+                if !valid_name(table) {
+                    return Err(protocol_error(format!("invalid table name: {}", table)));
+                }
+            }
+            match fields.as_slice() {
+                ["ROOTS"] => {
+                    let roots: Vec<String> = self.service.director.triples.list_info_tables()
+                        .into_iter()
+                        .map(|x| format!("{} {}", x, self.service.director.triples.get_table_root(&x)))
+                        .collect();
+                    peer.send_list("ROOT", &roots)?;
+                },
+                ["RANGE", table, from, to] => {
+                    let ids: Vec<String> = ids_in_range(self.ids(table), decode_bound(from), decode_bound(to)).into_iter().cloned().collect();
+                    peer.send(&format!("FINGERPRINT {} {}", ids.len(), MerkleTree::from_ids(ids).root()))?;
+                },
+                ["IDS", table, from, to] => {
+                    let ids: Vec<String> = ids_in_range(self.ids(table), decode_bound(from), decode_bound(to)).into_iter().cloned().collect();
+                    peer.send_list("ID", &ids)?;
+                },
+                ["GET", table, count] => {
+                    let ids = peer.read_block(count)?;
+                    let triples: Vec<String> = ids
+                        .iter()
+                        .filter_map(|x| self.service.director.triples.storage.get_triple(table, x))
+                        .collect();
+                    peer.send_list("TRIPLE", &triples)?;
+                },
+                ["PUT", table, count] => {
+                    let triples = peer.read_block(count)?;
+                    if let Some(bad) = triples.iter().find(|x| !valid_triple(x)) {
+                        return Err(protocol_error(format!("invalid triple: {}", bad)));
+                    }
+                    let known = self.ids(table);
+                    let mut new_ids: BTreeSet<&str> = BTreeSet::new();
+                    let new: Vec<String> = triples
+                        .iter()
+                        .filter(|x| {
+                            let id = x.split_once(' ').map_or(x.as_str(), |(id, _)| id);
+                            !known.contains(id) && new_ids.insert(id)
+                        })
+                        .cloned()
+                        .collect();
+                    let added = new.len();
+                    if added > 0 {
+                        self.service.director.triples.storage.append_many(table, new);
+                    }
+                    self.ids.remove(*table);
+                    peer.send(&format!("OK {}", added))?;
+                },
+                ["BYE"] => return Ok(()),
+                _ => return Err(protocol_error(format!("unknown command: {}", command))),
+            }
+        }
+    }
+
+    ///
+    /// Returns the ids of a table, read once per connection and dropped when the table is written.
+    ///
+    fn ids(&mut self, table: &str) -> &BTreeSet<String> {
+        if !self.ids.contains_key(table) {
+            let ids: BTreeSet<String> = if self.service.director.triples.list_info_tables().iter().any(|x| x == table) {
+                self.service.director.triples.storage.get_info_table(table)
+                    .lines()
+                    .map(|x| x.split_once(' ').map_or(x, |(id, _)| id).to_string())
+                    .collect()
+            } else {
+                BTreeSet::new()
+            };
+            self.ids.insert(table.to_string(), ids);
+        }
+        &self.ids[table]
+    }
+}
+
+///
+/// One end of a sync connection, reading and writing lines.
+///
+struct Peer {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl Peer {
+
+    fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+        Ok(Peer {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+        })
+    }
+
+    fn read_line(&mut self) -> io::Result<String> {
+        let mut line = String::new();
+        //room for the newline tells a line at the limit from a longer one
+        let read = (&mut self.reader).take(MAX_LINE_LEN as u64 + 1).read_line(&mut line)?;
+        if read == 0 {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "sync connection closed"));
+        }
+        if read > MAX_LINE_LEN && !line.ends_with('\n') {
+            return Err(protocol_error(format!("line longer than {} bytes", MAX_LINE_LEN)));
+        }
+        let line = line.trim_end_matches(['\n', '\r']).to_string();
+        match line.strip_prefix("ERROR ") {
+            Some(message) => Err(protocol_error(format!("peer error: {}", message))),
+            None => Ok(line),
+        }
+    }
+
+    ///
+    /// Tells the peer what was wrong with its answer and returns the error for it.
+    ///
+    fn reject(&mut self, message: String) -> io::Error {
+        let _ = self.send(&format!("ERROR {}", message));
+        protocol_error(message)
+    }
+
+    fn send(&mut self, line: &str) -> io::Result<()> {
+        writeln!(self.writer, "{}", line)?;
+        self.writer.flush()
+    }
+
+    fn request(&mut self, line: &str) -> io::Result<String> {
+        self.send(line)?;
+        self.read_line()
+    }
+
+    fn send_block(&mut self, command: &str, lines: &[String]) -> io::Result<()> {
+        writeln!(self.writer, "{}", command)?;
+        for line in lines {
+            writeln!(self.writer, "{}", line)?;
+        }
+        self.writer.flush()
+    }
+
+    fn read_block(&mut self, count: &str) -> io::Result<Vec<String>> {
+        let count = count.parse::<usize>().map_err(|_| protocol_error(format!("invalid count: {}", count)))?;
+        (0..count).map(|_| self.read_line()).collect()
+    }
+
+    fn send_list(&mut self, prefix: &str, items: &[String]) -> io::Result<()> {
+        for item in items {
+            writeln!(self.writer, "{} {}", prefix, item)?;
+        }
+        self.send("END")
+    }
+
+    fn read_list(&mut self, prefix: &str) -> io::Result<Vec<String>> {
+        let mut items: Vec<String> = Vec::new();
+        loop {
+            let line = self.read_line()?;
+            if line == "END" {
+                return Ok(items);
+            }
+            match line.strip_prefix(prefix).and_then(|x| x.strip_prefix(' ')) {
+                Some(item) => items.push(item.to_string()),
+                None => return Err(protocol_error(format!("expected {}, got: {}", prefix, line))),
+            }
+        }
+    }
+
+    fn read_answer(&mut self, prefix: &str) -> io::Result<String> {
+        let line = self.read_line()?;
+        match line.strip_prefix(prefix).and_then(|x| x.strip_prefix(' ')) {
+            Some(answer) => Ok(answer.to_string()),
+            None => Err(protocol_error(format!("expected {}, got: {}", prefix, line))),
+        }
+    }
+}
+
+fn ids_in_range<'a>(ids: &'a BTreeSet<String>, from: Option<&str>, to: Option<&str>) -> Vec<&'a String> {
+    //BTreeSet::range() panics on a reversed range
+    if from.zip(to).is_some_and(|(from, to)| from > to) {
+        return Vec::new();
+    }
+    let from = from.map_or(Bound::Unbounded, Bound::Included);
+    let to = to.map_or(Bound::Unbounded, Bound::Excluded);
+    ids.range::<str, _>((from, to)).collect()
+}
+
+fn encode_bound(bound: Option<&str>) -> &str {
+    bound.unwrap_or("-")
+}
+
+fn decode_bound(bound: &str) -> Option<&str> {
+    match bound {
+        "-" => None,
+        x => Some(x),
+    }
+}
+
+fn protocol_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+
+#[test]
+fn sync_with_peer_test() {
+    let _ = std::fs::remove_dir_all("tcp_sync_test_root_a");
+    let _ = std::fs::remove_dir_all("tcp_sync_test_root_b");
+    let mut a = TrServiceFS::new_in("tcp_sync_test_root_a", "tcp_sync_test_space".to_string());
+    let mut b = TrServiceFS::new_in("tcp_sync_test_root_b", "tcp_sync_test_space".to_string());
+    let pairs: Vec<(String, String)> = (0..100).map(|i| (format!("id1-{}", i), format!("id2-{}", i))).collect();
+    a.create_infotriples(pairs.clone());
+    b.create_infotriples(pairs);
+    a.create_infotriple("id1-a".to_string(), "id2-a".to_string());
+    b.create_infotriple("id1-b".to_string(), "id2-b".to_string());
+    b.director.triples.storage.append_info_table("b_table", &hashing_tools::concat_n_hash("id1-x", "id2-x"));
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = std::thread::spawn(move || {
+        serve(&mut b, &listener, Some(2)).unwrap();
+        b
+    });
+
+    let report = sync_with_peer(&mut a, addr, true).unwrap();
+    assert_eq!(report.total_added_here(), 2);
+    assert_eq!(report.total_added_there(), 1);
+    assert_eq!(a.get_all_info_triples_from_info_table("main_table".to_string()).len(), 101);
+
+    let report = sync_with_peer(&mut a, addr, false).unwrap();
+    assert_eq!(report.tables["main_table"], TableSync { added_here: 1, added_there: 1 });
+    let mut b = server.join().unwrap();
+    assert_eq!(a.get_space_root(), b.get_space_root());
+    assert_eq!(b.get_all_info_triples_from_info_table("main_table".to_string()).len(), 102);
+
+    let _ = std::fs::remove_dir_all("tcp_sync_test_root_a");
+    let _ = std::fs::remove_dir_all("tcp_sync_test_root_b");
+}
+
+#[test]
+fn valid_name_and_triple_test() {
+    assert!(valid_name("main_table"));
+    for name in ["", "..", "a/b", "a\\b", "a b", "a\tb", "a\u{0}b", "../main_table"] {
+        assert!(!valid_name(name), "{:?}", name);
+    }
+    let triple = hashing_tools::concat_n_hash("id1-x", "id2-x");
+    assert!(valid_triple(&triple));
+    assert!(!valid_triple(&triple.replacen("id1-x", "id1-y", 1)));
+    assert!(!valid_triple("id-x id1-x id2-x"));
+    assert!(!valid_triple("id-x"));
+}
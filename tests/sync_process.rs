@@ -0,0 +1,102 @@
+//! Syncs with a sync server running in a second process: the test binary started again, running
+//! only sync_server_process().
+
+use std::env;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{Shutdown, TcpListener, TcpStream};
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+
+use ig_tr::tr_service_fs::TrServiceFS;
+use ig_tr::tr_sync_fs::MAX_LINE_LEN;
+
+const SERVER_ROOT_ENV: &str = "IG_TR_SYNC_SERVER_ROOT";
+const SPACE: &str = "process_sync_space";
+
+///
+/// Kills the server process when the test ends, also when it fails.
+///
+struct Server(Child);
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.0.kill();
+        let _ = self.0.wait();
+    }
+}
+
+#[test]
+fn sync_server_process() {
+    //only serves when started by sync_with_server_process_test()
+    let Ok(root) = env::var(SERVER_ROOT_ENV) else { return };
+    let mut service = TrServiceFS::new_in(&root, SPACE.to_string());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    println!("serving on {}", listener.local_addr().unwrap());
+    service.serve_sync(&listener, None).unwrap();
+}
+
+///
+/// Sends lines on a new connection, closes the sending side and returns the last answer, so all
+/// that is sent is read by the server before it ends the connection.
+///
+fn exchange(addr: &str, lines: &[&str]) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(lines.join("\n").as_bytes()).unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+    BufReader::new(stream)
+        .lines()
+        .map_while(|x| x.ok())
+        .last()
+        .unwrap_or_default()
+}
+
+#[test]
+fn sync_with_server_process_test() {
+    let (root_a, root_b) = ("process_sync_test_root_a", "process_sync_test_root_b");
+    let _ = std::fs::remove_dir_all(root_a);
+    let _ = std::fs::remove_dir_all(root_b);
+    let mut a = TrServiceFS::new_in(root_a, SPACE.to_string());
+    let mut b = TrServiceFS::new_in(root_b, SPACE.to_string());
+    let pairs: Vec<(String, String)> = (0..50).map(|i| (format!("id1-{}", i), format!("id2-{}", i))).collect();
+    a.create_infotriples(pairs.clone());
+    b.create_infotriples(pairs);
+    a.create_infotriple("id1-a".to_string(), "id2-a".to_string());
+    b.create_infotriple("id1-b".to_string(), "id2-b".to_string());
+    drop(b);
+
+    let child = Command::new(env::current_exe().unwrap())
+        .args(["sync_server_process", "--exact", "--nocapture", "--test-threads=1"])
+        .env(SERVER_ROOT_ENV, root_b)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut server = Server(child);
+    //the test harness prints the test name on the same line
+    let mut output = BufReader::new(server.0.stdout.take().unwrap()).lines();
+    let addr = output
+        .find_map(|x| x.ok()?.split_once("serving on ").map(|(_, addr)| addr.to_string()))
+        .expect("the server process did not start serving");
+    //keeps reading, so the server does not block on or fail writing its output
+    std::thread::spawn(move || output.for_each(drop));
+
+    let report = a.sync_with_peer(addr.as_str(), false).unwrap();
+    assert_eq!(report.total_added_here(), 1);
+    assert_eq!(report.total_added_there(), 1);
+
+    let hello = format!("HELLO ig_tr_sync 1 {}", SPACE);
+    assert!(exchange(&addr, &["HELLO ig_tr_sync 1 ../escaped_space"]).starts_with("ERROR"));
+    assert!(!Path::new(root_b).join("files/escaped_space").exists());
+    assert!(exchange(&addr, &[&hello, "IDS ../main_table - -"]).starts_with("ERROR"));
+    assert!(exchange(&addr, &[&hello, "PUT main_table 1", "forged-id id1-f id2-f"]).starts_with("ERROR"));
+    assert!(exchange(&addr, &[&hello, &"x".repeat(MAX_LINE_LEN + 1)]).starts_with("ERROR"));
+
+    //the server still serves after the broken connections
+    assert_eq!(a.sync_with_peer(addr.as_str(), true).unwrap().total_added_here(), 0);
+    drop(server);
+    let mut b = TrServiceFS::new_in(root_b, SPACE.to_string());
+    assert_eq!(a.get_space_root(), b.get_space_root());
+    assert_eq!(b.get_all_info_triples_from_info_table("main_table".to_string()).len(), 52);
+
+    let _ = std::fs::remove_dir_all(root_a);
+    let _ = std::fs::remove_dir_all(root_b);
+}